@@ -0,0 +1,139 @@
+pub mod matcher {
+    /// Score for every matched character.
+    const SCORE_MATCH: i64 = 16;
+    /// Bonus for a match right after a `/`, like the start of `billing` in `feature/billing`.
+    const BONUS_SLASH: i64 = 30;
+    /// Bonus for a match at the start of the name or after a `-`, `_`, `.` or space.
+    const BONUS_BOUNDARY: i64 = 24;
+    /// Bonus for a match on an uppercase letter following a lowercase one.
+    const BONUS_CAMEL: i64 = 16;
+    /// Bonus for a match directly following the previous match.
+    const BONUS_CONSECUTIVE: i64 = 20;
+    /// Penalty for every character skipped between two matches.
+    const PENALTY_GAP: i64 = 1;
+
+    fn bonus(candidate: &[char], index: usize) -> i64 {
+        let Some(previous) = index.checked_sub(1).map(|i| candidate[i]) else {
+            return BONUS_BOUNDARY;
+        };
+        match previous {
+            '/' => BONUS_SLASH,
+            '-' | '_' | '.' | ' ' => BONUS_BOUNDARY,
+            _ if previous.is_lowercase() && candidate[index].is_uppercase() => BONUS_CAMEL,
+            _ => 0,
+        }
+    }
+
+    /// Matches `pattern` as a case-insensitive subsequence of `candidate`.
+    /// Returns the score of the best alignment, favouring word boundaries and
+    /// consecutive runs, along with the char indices of the matched characters.
+    pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+        let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+        let candidate: Vec<char> = candidate.chars().collect();
+        let lowered: Vec<char> = candidate
+            .iter()
+            .map(|c| c.to_lowercase().next().unwrap_or(*c))
+            .collect();
+        if pattern.is_empty() {
+            return Some((0, Vec::new()));
+        }
+        if pattern.len() > candidate.len() {
+            return None;
+        }
+
+        // scores[i][j]: best score with pattern[i] matched at candidate[j],
+        // and from[i][j] the position pattern[i - 1] was matched at for it
+        let n = candidate.len();
+        let mut scores: Vec<Vec<Option<i64>>> = vec![vec![None; n]; pattern.len()];
+        let mut from: Vec<Vec<usize>> = vec![vec![0; n]; pattern.len()];
+        for (i, p) in pattern.iter().enumerate() {
+            for j in i..n {
+                if lowered[j] != *p {
+                    continue;
+                }
+                let here = SCORE_MATCH + bonus(&candidate, j);
+                if i == 0 {
+                    scores[i][j] = Some(here);
+                    continue;
+                }
+                let best = (i - 1..j)
+                    .filter_map(|k| {
+                        scores[i - 1][k].map(|score| {
+                            let linked = if k + 1 == j {
+                                BONUS_CONSECUTIVE
+                            } else {
+                                -PENALTY_GAP * (j - k - 1) as i64
+                            };
+                            (score + linked, k)
+                        })
+                    })
+                    .max_by_key(|(score, _)| *score);
+                if let Some((score, k)) = best {
+                    scores[i][j] = Some(score + here);
+                    from[i][j] = k;
+                }
+            }
+        }
+
+        let last = pattern.len() - 1;
+        let (score, mut j) = (0..n)
+            .filter_map(|j| scores[last][j].map(|score| (score, j)))
+            .max_by_key(|(score, _)| *score)?;
+        let mut positions = vec![0; pattern.len()];
+        for i in (0..pattern.len()).rev() {
+            positions[i] = j;
+            j = from[i][j];
+        }
+        Some((score, positions))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::fuzzy_match;
+
+        fn positions(pattern: &str, candidate: &str) -> Option<Vec<usize>> {
+            fuzzy_match(pattern, candidate).map(|(_, positions)| positions)
+        }
+
+        #[test]
+        fn empty_pattern_matches_anything() {
+            assert_eq!(fuzzy_match("", "main"), Some((0, Vec::new())));
+            assert_eq!(fuzzy_match("", ""), Some((0, Vec::new())));
+        }
+
+        #[test]
+        fn needs_every_character_in_order() {
+            assert_eq!(positions("mn", "main"), Some(vec![0, 3]));
+            assert_eq!(positions("nm", "main"), None);
+            assert_eq!(positions("mainline", "main"), None);
+            assert_eq!(positions("x", ""), None);
+        }
+
+        #[test]
+        fn ignores_case() {
+            assert_eq!(positions("FB", "feature/billing"), Some(vec![0, 8]));
+            assert_eq!(positions("fix", "Hot-FIX"), Some(vec![4, 5, 6]));
+        }
+
+        #[test]
+        fn positions_count_chars_not_bytes() {
+            assert_eq!(positions("fé", "café-fix"), Some(vec![2, 3]));
+            assert_eq!(positions("x", "café-fix"), Some(vec![7]));
+        }
+
+        #[test]
+        fn prefers_word_boundaries() {
+            assert_eq!(positions("b", "abc/bar"), Some(vec![4]));
+            assert_eq!(positions("b", "ab-b"), Some(vec![3]));
+            assert_eq!(positions("t", "fooTbar"), Some(vec![3]));
+        }
+
+        #[test]
+        fn prefers_consecutive_runs() {
+            assert_eq!(positions("bar", "bxaxrbar"), Some(vec![5, 6, 7]));
+            let (together, _) = fuzzy_match("abc", "abcxx").unwrap();
+            let (apart, _) = fuzzy_match("abc", "axbxc").unwrap();
+            assert!(together > apart);
+        }
+    }
+}
@@ -1,8 +1,25 @@
 pub mod branching {
+    use crate::settings::config::Settings;
     use chrono::{DateTime, Utc};
-    use clap::Parser;
-    use git2::{BranchType, Repository};
-    use std::time::Duration;
+    use clap::{Parser, Subcommand, ValueEnum};
+    use git2::build::CheckoutBuilder;
+    use git2::{
+        Branch, BranchType, Cred, CredentialType, DescribeOptions, DiffFormat, DiffStatsFormat, ErrorCode,
+        FetchOptions, Oid, PushOptions, RebaseOptions, RemoteCallbacks, Repository,
+        RepositoryOpenFlags, Signature, StatusOptions, WorktreeAddOptions,
+    };
+    use serde::{Deserialize, Serialize};
+    use std::cell::RefCell;
+    use std::collections::{BinaryHeap, HashMap, HashSet};
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::mpsc::{self, Receiver};
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
     use timeago::Formatter;
 
     #[derive(Debug, Eq, PartialEq, Clone)]
@@ -10,17 +27,555 @@ pub mod branching {
         pub branch_name: String,
         pub last_commit_time: i64,
         pub time_ago: String,
+        /// Last time HEAD moved to or away from this branch, according to the reflog.
+        pub last_visited: Option<i64>,
+        pub visited_ago: Option<String>,
         pub is_head: bool,
+        /// Commits ahead of and behind the upstream, if there is one.
+        pub ahead_behind: Option<(usize, usize)>,
         pub remote_tracking: Option<String>,
+        /// Name of the remote for remote-tracking branches, None for local ones.
+        pub remote: Option<String>,
+        /// Whether all of the branch's changes are already in the base branch.
+        pub merged: bool,
+        /// Whether the branch has an upstream configured that no longer exists.
+        pub upstream_gone: bool,
+        /// Where the branch is checked out, if in a worktree other than this one.
+        pub worktree: Option<PathBuf>,
+        /// Set for the row describing HEAD when it isn't on an existing branch.
+        pub head_row: Option<HeadRow>,
+    }
+
+    impl BranchInfo {
+        /// Whether the row is a branch, rather than a detached HEAD.
+        pub fn is_branch(&self) -> bool {
+            !matches!(self.head_row, Some(HeadRow::Detached { .. }))
+        }
+    }
+
+    /// What HEAD is when it isn't on a branch with commits.
+    #[derive(Debug, Eq, PartialEq, Clone)]
+    pub enum HeadRow {
+        /// HEAD points at a commit directly. The description is the nearest
+        /// tag like `git describe --tags`, or the commit's summary.
+        Detached { short_id: String, description: String },
+        /// HEAD is on a branch that has no commits yet, as in a new repository.
+        Unborn,
+    }
+
+    /// One line of a branch's commit log.
+    #[derive(Debug, Eq, PartialEq, Clone)]
+    pub struct CommitSummary {
+        pub short_id: String,
+        pub author: String,
+        pub time_ago: String,
+        pub summary: String,
+    }
+
+    /// A local branch as it was just before githist deleted it, one line of
+    /// the deletion journal.
+    #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+    pub struct DeletedBranch {
+        /// The deletion this was part of; undo restores a whole one at once.
+        pub step: u128,
+        pub name: String,
+        pub tip: String,
+        pub remote: Option<String>,
+        pub merge: Option<String>,
+        pub deleted_at: i64,
+    }
+
+    /// Groups the branches deleted together, so that one undo restores them
+    /// all.
+    #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+    pub struct UndoStep(u128);
+
+    impl UndoStep {
+        pub fn new() -> UndoStep {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            UndoStep(now.as_nanos())
+        }
+    }
+
+    impl Default for UndoStep {
+        fn default() -> Self {
+            UndoStep::new()
+        }
+    }
+
+    /// A branch an undo tried to restore, and whether that worked.
+    pub type Restored = (String, Result<(), git2::Error>);
+
+    /// Why nothing can be checked out in a bare repository.
+    pub const BARE_CHECKOUT: &str =
+        "this is a bare repository, which has no working tree to check branches out in";
+
+    /// How many deleted branches the journal remembers.
+    const JOURNAL_LIMIT: usize = 200;
+
+    /// What a line of a branch diff represents, used for coloring.
+    #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+    pub enum DiffLineKind {
+        Stat,
+        FileHeader,
+        HunkHeader,
+        Added,
+        Removed,
+        Context,
+    }
+
+    #[derive(Debug, Eq, PartialEq, Clone)]
+    pub struct DiffLine {
+        pub kind: DiffLineKind,
+        pub text: String,
+    }
+
+    /// How to treat local changes when switching branches.
+    #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+    pub enum SwitchStrategy {
+        /// Carry local changes over, failing if they conflict with the target.
+        Carry,
+        /// Stash local changes before switching.
+        Stash,
+        /// Throw local changes away.
+        Force,
+    }
+
+    fn relative_time(formatter: &Formatter, now: DateTime<Utc>, timestamp: i64) -> String {
+        // commit times far outside chrono's range are shown as the epoch
+        let datetime: DateTime<Utc> = DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+        formatter.convert_chrono(datetime, now)
+    }
+
+    /// How many base branch commits are searched for squash or rebase merges,
+    /// and how many commits a branch may have for a rebase merge to be found.
+    const MERGE_SCAN_LIMIT: usize = 1000;
+
+    /// Decides whether branches are merged into the base branch, either as
+    /// ancestors of it or by having their changes squashed, rebased or
+    /// cherry-picked onto it. Commit patch-ids are cached across branches.
+    struct MergeDetector<'r> {
+        repo: &'r Repository,
+        base: Oid,
+        patch_ids: HashMap<Oid, Option<Oid>>,
+        /// Once set, remaining checks skip the expensive work and their
+        /// answers are meaningless.
+        cancelled: Option<&'r AtomicBool>,
+    }
+
+    impl<'r> MergeDetector<'r> {
+        fn new(
+            repo: &'r Repository,
+            base_name: &str,
+            cancelled: Option<&'r AtomicBool>,
+        ) -> Option<MergeDetector<'r>> {
+            let base = repo.revparse_single(base_name).ok()?.peel_to_commit().ok()?.id();
+            Some(MergeDetector {
+                repo,
+                base,
+                patch_ids: HashMap::new(),
+                cancelled,
+            })
+        }
+
+        fn is_cancelled(&self) -> bool {
+            self.cancelled.is_some_and(|flag| flag.load(Ordering::Relaxed))
+        }
+
+        /// Patch-id of the changes a commit introduces, None for merge commits.
+        fn commit_patch_id(&mut self, oid: Oid) -> Option<Oid> {
+            if let Some(patch_id) = self.patch_ids.get(&oid) {
+                return *patch_id;
+            }
+            if self.is_cancelled() {
+                return None;
+            }
+            let patch_id = (|| {
+                let commit = self.repo.find_commit(oid).ok()?;
+                if commit.parent_count() > 1 {
+                    return None;
+                }
+                let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+                let diff = self
+                    .repo
+                    .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree().ok()?), None)
+                    .ok()?;
+                diff.patchid(None).ok()
+            })();
+            self.patch_ids.insert(oid, patch_id);
+            patch_id
+        }
+
+        /// Up to `limit` commits reachable from `tip` but not from `hide`,
+        /// newest first.
+        fn commits_between(&self, tip: Oid, hide: Oid, limit: usize) -> Vec<Oid> {
+            let Ok(mut revwalk) = self.repo.revwalk() else {
+                return Vec::new();
+            };
+            if revwalk.push(tip).is_err() || revwalk.hide(hide).is_err() {
+                return Vec::new();
+            }
+            revwalk
+                .filter_map(Result::ok)
+                .take(limit)
+                .take_while(|_| !self.is_cancelled())
+                .collect()
+        }
+
+        fn is_merged(&mut self, tip: Oid) -> bool {
+            if tip == self.base || self.repo.graph_descendant_of(self.base, tip).unwrap_or(false) {
+                return true;
+            }
+            let Ok(merge_base) = self.repo.merge_base(self.base, tip) else {
+                return false;
+            };
+            let base_patch_ids: HashSet<Oid> = self
+                .commits_between(self.base, merge_base, MERGE_SCAN_LIMIT)
+                .into_iter()
+                .filter_map(|oid| self.commit_patch_id(oid))
+                .collect();
+            if base_patch_ids.is_empty() {
+                return false;
+            }
+
+            // squash merge: the branch's whole diff landed as a single commit
+            let squashed = (|| {
+                let old_tree = self.repo.find_commit(merge_base).ok()?.tree().ok()?;
+                let new_tree = self.repo.find_commit(tip).ok()?.tree().ok()?;
+                let diff = self
+                    .repo
+                    .diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)
+                    .ok()?;
+                diff.patchid(None).ok()
+            })();
+            if squashed.is_some_and(|patch_id| base_patch_ids.contains(&patch_id)) {
+                return true;
+            }
+
+            // rebase or cherry-pick: every commit on the branch has an equivalent
+            // on base. a branch too long to check in full isn't taken as merged
+            let branch_commits = self.commits_between(tip, merge_base, MERGE_SCAN_LIMIT + 1);
+            branch_commits.len() <= MERGE_SCAN_LIMIT
+                && branch_commits.into_iter().all(|oid| {
+                    self.commit_patch_id(oid)
+                        .is_some_and(|patch_id| base_patch_ids.contains(&patch_id))
+                })
+        }
+    }
+
+    /// Commits a local branch is ahead of and behind its upstream.
+    fn ahead_behind(repo: &Repository, branch_name: &str) -> Option<(usize, usize)> {
+        let branch = repo.find_branch(branch_name, BranchType::Local).ok()?;
+        let (local_oid, upstream_oid) = upstream_tips(&branch)?;
+        repo.graph_ahead_behind(local_oid, upstream_oid).ok()
+    }
+
+    /// The commits a local branch and its upstream point at.
+    fn upstream_tips(branch: &Branch) -> Option<(Oid, Oid)> {
+        let upstream = branch.upstream().ok()?;
+        Some((branch.get().target()?, upstream.get().target()?))
+    }
+
+    /// How many commits `bounded_ahead_behind` visits before giving up.
+    const AHEAD_BEHIND_LIMIT: usize = 20_000;
+
+    /// Shown instead of the counts when `bounded_ahead_behind` gave up.
+    const TOO_FAR_APART: &str = "too far apart to count";
+
+    /// Counts like `graph_ahead_behind`, walking both histories newest first
+    /// until everything left to visit is shared by them, but returns None
+    /// once more than `limit` commits were visited or `cancelled` is set.
+    fn bounded_ahead_behind(
+        repo: &Repository,
+        local: Oid,
+        upstream: Oid,
+        limit: usize,
+        cancelled: &AtomicBool,
+    ) -> Option<(usize, usize)> {
+        const LOCAL: u8 = 1;
+        const UPSTREAM: u8 = 2;
+        const BOTH: u8 = LOCAL | UPSTREAM;
+        if local == upstream {
+            return Some((0, 0));
+        }
+        let commit_time = |oid: Oid| repo.find_commit(oid).map(|c| c.time().seconds());
+        let mut flags: HashMap<Oid, u8> = HashMap::from([(local, LOCAL), (upstream, UPSTREAM)]);
+        let mut queue = BinaryHeap::from([
+            (commit_time(local).ok()?, local),
+            (commit_time(upstream).ok()?, upstream),
+        ]);
+        let mut visited = 0;
+        while queue.iter().any(|(_, oid)| flags[oid] != BOTH) {
+            let Some((_, oid)) = queue.pop() else {
+                break;
+            };
+            visited += 1;
+            if visited > limit || cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            let reached = flags[&oid];
+            for parent in repo.find_commit(oid).ok()?.parents() {
+                let flag = flags.entry(parent.id()).or_default();
+                if *flag | reached != *flag {
+                    *flag |= reached;
+                    queue.push((parent.time().seconds(), parent.id()));
+                }
+            }
+        }
+        let only = |side: u8| flags.values().filter(|&&flag| flag == side).count();
+        Some((only(LOCAL), only(UPSTREAM)))
+    }
+
+    fn has_upstream(repo: &Repository, branch_name: &str) -> bool {
+        repo.config()
+            .and_then(|config| config.get_string(&format!("branch.{branch_name}.merge")))
+            .is_ok()
+    }
+
+    /// The upstream summary shown next to a local branch.
+    fn remote_tracking(ahead_behind: Option<(usize, usize)>, upstream_gone: bool) -> Option<String> {
+        if upstream_gone {
+            Some("gone".to_string())
+        } else {
+            ahead_behind.map(Repo::remote_tracking_info)
+        }
+    }
+
+    /// Cached values, keyed by the commits they were worked out from.
+    #[derive(Serialize, Deserialize, Default)]
+    struct CacheEntries {
+        /// Ahead and behind counts by `<local> <upstream>` commit ids.
+        ahead_behind: HashMap<String, (usize, usize)>,
+        /// Merged state by `<tip> <base>` commit ids.
+        merged: HashMap<String, bool>,
+    }
+
+    /// Ahead/behind counts and merged states kept in `.git/githist/cache`
+    /// between runs. Entries are looked up by the commits the refs point at,
+    /// so they never go stale: once a ref moves they just stop being used,
+    /// and saving keeps only the entries used since loading.
+    struct DetailsCache {
+        /// None when caching is turned off.
+        path: Option<PathBuf>,
+        saved: CacheEntries,
+        used: CacheEntries,
+    }
+
+    impl DetailsCache {
+        /// Reads the cache at `path`, starting empty if it is missing or
+        /// can't be parsed.
+        fn load(path: Option<PathBuf>) -> DetailsCache {
+            let saved = path
+                .as_ref()
+                .and_then(|path| fs::read_to_string(path).ok())
+                .and_then(|contents| serde_json::from_str(&contents).ok())
+                .unwrap_or_default();
+            DetailsCache {
+                path,
+                saved,
+                used: CacheEntries::default(),
+            }
+        }
+
+        /// The cached counts, or those `compute` returns, which are cached
+        /// unless None.
+        fn ahead_behind(
+            &mut self,
+            local: Oid,
+            upstream: Oid,
+            compute: impl FnOnce() -> Option<(usize, usize)>,
+        ) -> Option<(usize, usize)> {
+            let key = format!("{local} {upstream}");
+            let counts = self.used.ahead_behind.get(&key).copied();
+            let counts = counts
+                .or_else(|| self.saved.ahead_behind.get(&key).copied())
+                .or_else(compute)?;
+            self.used.ahead_behind.insert(key, counts);
+            Some(counts)
+        }
+
+        /// The cached merged state, or the one `compute` returns, which is
+        /// cached unless None.
+        fn merged(&mut self, tip: Oid, base: Oid, compute: impl FnOnce() -> Option<bool>) -> Option<bool> {
+            let key = format!("{tip} {base}");
+            let merged = self.used.merged.get(&key).copied();
+            let merged = merged
+                .or_else(|| self.saved.merged.get(&key).copied())
+                .or_else(compute)?;
+            self.used.merged.insert(key, merged);
+            Some(merged)
+        }
+
+        /// Writes the entries used since loading back, along with the ones
+        /// loaded but not needed yet unless every branch was looked up.
+        /// Failing to save only costs recomputing them next time, so errors
+        /// are ignored.
+        fn save(mut self, complete: bool) {
+            let Some(path) = self.path.take() else {
+                return;
+            };
+            if !complete {
+                for (key, counts) in self.saved.ahead_behind {
+                    self.used.ahead_behind.entry(key).or_insert(counts);
+                }
+                for (key, merged) in self.saved.merged {
+                    self.used.merged.entry(key).or_insert(merged);
+                }
+            }
+            let Ok(contents) = serde_json::to_string(&self.used) else {
+                return;
+            };
+            // written aside and renamed so no other save, from this githist or
+            // another one, reads or overwrites half of it
+            static SAVES: AtomicUsize = AtomicUsize::new(0);
+            let save = SAVES.fetch_add(1, Ordering::Relaxed);
+            let partial = path.with_extension(format!("{}.{save}", std::process::id()));
+            let written = path.parent().is_some_and(|dir| fs::create_dir_all(dir).is_ok())
+                && fs::write(&partial, contents).is_ok();
+            if !written || fs::rename(&partial, &path).is_err() {
+                let _ = fs::remove_file(&partial);
+            }
+        }
+    }
+
+    /// How many finished `BranchDetails` may wait to be received before the
+    /// loader stops to let the UI catch up.
+    const DETAILS_QUEUE: usize = 256;
+
+    /// The fields of a local branch that need a walk of its history, as
+    /// computed by a `DetailsLoader`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BranchDetails {
+        pub branch_name: String,
+        pub ahead_behind: Option<(usize, usize)>,
+        pub remote_tracking: Option<String>,
+        pub merged: bool,
+    }
+
+    impl BranchDetails {
+        /// Copies the details into the branch's row.
+        pub fn apply(&self, branch: &mut BranchInfo) {
+            branch.ahead_behind = self.ahead_behind;
+            branch.remote_tracking.clone_from(&self.remote_tracking);
+            branch.merged = self.merged;
+        }
+    }
+
+    /// Computes `BranchDetails` on a thread of its own, one branch at a time
+    /// in list order, sending each over `receiver` as soon as it is known.
+    /// The work stops when `cancel` is called or the loader is dropped.
+    pub struct DetailsLoader {
+        pub receiver: Receiver<BranchDetails>,
+        cancelled: Arc<AtomicBool>,
+        worker: Option<JoinHandle<()>>,
+    }
+
+    impl DetailsLoader {
+        pub fn cancel(&self) {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+
+        /// Cancels the work and waits for the thread to save what it has
+        /// cached so far, so that isn't lost when githist exits.
+        pub fn finish(mut self) {
+            self.cancel();
+            // a worker blocked on a full queue gives up once the receiver is gone
+            self.receiver = mpsc::sync_channel(0).1;
+            if let Some(worker) = self.worker.take() {
+                let _ = worker.join();
+            }
+        }
+    }
+
+    impl Drop for DetailsLoader {
+        fn drop(&mut self) {
+            self.cancel();
+        }
+    }
+
+    /// How the branch list is ordered.
+    #[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum SortOrder {
+        /// Most recently checked out first, like `git checkout -`
+        Checkout,
+        /// Most recent tip commit first
+        Commit,
+    }
+
+    /// Output formats for `githist list`.
+    #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ListFormat {
+        /// Aligned columns, like `git branch -vv`
+        Plain,
+        /// Tab-separated: name, timestamp, relative age, head, ahead, behind
+        Tsv,
+        /// A JSON array of branch objects
+        Json,
+    }
+
+    #[derive(Subcommand, Debug)]
+    pub enum Command {
+        /// Print the branches in githist's order and exit
+        List {
+            #[arg(long, value_enum, default_value_t = ListFormat::Plain)]
+            format: ListFormat,
+        },
+        /// Forget the cached ahead/behind counts and merge states and exit
+        ClearCache,
     }
 
     /// A TUI for quickly switching between recent Git branches
     #[derive(Parser, Debug)]
     #[command(version, about)]
     pub struct Config {
-        /// Path to the git repository
-        #[arg(default_value = ".")]
-        pub repo_path: String,
+        /// Path to the git repository, or any directory inside it [default: the
+        /// current directory, or $GIT_DIR if set]
+        pub repo_path: Option<String>,
+
+        /// Also list remote-tracking branches
+        #[arg(short = 'a', long = "all", global = true, overrides_with = "local_only")]
+        pub include_remotes: bool,
+
+        /// Only list local branches, even if the config file says otherwise
+        #[arg(long = "no-all", global = true, overrides_with = "include_remotes")]
+        pub local_only: bool,
+
+        /// Branch that diffs and merge checks are computed against [default: main]
+        #[arg(long, global = true)]
+        pub base: Option<String>,
+
+        /// Print the chosen branch to stdout instead of checking it out, drawing
+        /// the TUI on stderr. Exits with 1 if cancelled and 2 on errors
+        #[arg(long)]
+        pub print: bool,
+
+        /// Pop the stash githist made when leaving a branch on switching back to it
+        #[arg(long, overrides_with = "no_pop_autostash")]
+        pub pop_autostash: bool,
+
+        /// Leave autostashes alone, even if the config file says to pop them
+        #[arg(long, overrides_with = "pop_autostash")]
+        pub no_pop_autostash: bool,
+
+        /// Work out ahead/behind counts and merge states afresh instead of
+        /// reading and updating the cache in .git/githist/cache
+        #[arg(long, global = true)]
+        pub no_cache: bool,
+
+        /// Order branches by checkout recency or by tip commit time [default: checkout]
+        #[arg(long, value_enum, global = true)]
+        pub sort: Option<SortOrder>,
+
+        /// Color theme: terminal, light, dark, monochrome or one defined in the
+        /// config file [default: terminal, or monochrome if NO_COLOR is set]
+        #[arg(long)]
+        pub theme: Option<String>,
+
+        #[command(subcommand)]
+        pub command: Option<Command>,
 
         /// UI tick rate in milliseconds
         #[arg(long, default_value_t = 250, hide = true)]
@@ -28,23 +583,116 @@ pub mod branching {
     }
 
     impl Config {
+        /// `--all` or `--no-all`, whichever came last, if either was given.
+        pub fn include_remotes(&self) -> Option<bool> {
+            flag(self.include_remotes, self.local_only)
+        }
+
+        /// `--pop-autostash` or `--no-pop-autostash`, whichever came last, if
+        /// either was given.
+        pub fn pop_autostash(&self) -> Option<bool> {
+            flag(self.pop_autostash, self.no_pop_autostash)
+        }
+
         pub fn tick_rate(&self) -> Duration {
             Duration::from_millis(self.tick_rate_ms)
         }
     }
 
+    /// A flag and its negation, which clap never both sets.
+    fn flag(on: bool, off: bool) -> Option<bool> {
+        (on || off).then_some(on)
+    }
+
     /// Wrapper around a git2::Repository to avoid re-opening on every operation.
     pub struct Repo {
         inner: Repository,
+        include_remotes: bool,
+        sort: SortOrder,
+        base: String,
+        use_cache: bool,
     }
 
     impl Repo {
+        /// Opens the repository containing `config.repo_path`, or the current
+        /// directory, searching parent directories like git does and honouring
+        /// `GIT_CEILING_DIRECTORIES`, plus `GIT_DIR` and `GIT_WORK_TREE` when
+        /// no path is given.
+        ///
         /// # Errors
         ///
-        /// Will return `git2::Error` if not a valid repo.
+        /// Will return `git2::Error` with a readable message if no repository
+        /// was found or it couldn't be opened.
         pub fn open(config: &Config) -> Result<Repo, git2::Error> {
-            let inner = Repository::open(&config.repo_path)?;
-            Ok(Repo { inner })
+            let opened = match config.repo_path.as_deref() {
+                Some(path) => {
+                    // `FROM_ENV` would ignore the ceilings once a path is given
+                    let ceilings: Vec<PathBuf> = env::var_os("GIT_CEILING_DIRECTORIES")
+                        .map(|dirs| env::split_paths(&dirs).collect())
+                        .unwrap_or_default();
+                    Repository::open_ext(path, RepositoryOpenFlags::empty(), &ceilings)
+                }
+                None => Repository::open_from_env(),
+            };
+            let path = config.repo_path.as_deref();
+            let inner = opened.map_err(|error| Self::open_error(path, &error))?;
+            let defaults = Settings::default();
+            Ok(Repo {
+                inner,
+                include_remotes: defaults.include_remotes,
+                sort: defaults.sort,
+                base: defaults.base,
+                use_cache: !config.no_cache,
+            })
+        }
+
+        /// Rewords a failure to open a repository for people rather than for
+        /// debugging.
+        fn open_error(path: Option<&str>, error: &git2::Error) -> git2::Error {
+            let git_dir = env::var("GIT_DIR").ok().filter(|_| path.is_none());
+            let path = path.unwrap_or(".");
+            let message = match (error.code(), git_dir) {
+                (ErrorCode::NotFound, Some(git_dir)) => {
+                    format!("GIT_DIR={git_dir} is not a git repository")
+                }
+                (ErrorCode::NotFound, None) if !Path::new(path).exists() => {
+                    format!("{path} doesn't exist")
+                }
+                (ErrorCode::NotFound, None) => {
+                    format!("not a git repository (or any of the parent directories): {path}")
+                }
+                (_, _) => {
+                    format!("couldn't open the git repository at {path}: {}", error.message())
+                }
+            };
+            git2::Error::new(error.code(), error.class(), message)
+        }
+
+        /// Whether the repository has no working tree, so branches can be
+        /// listed but not checked out.
+        pub fn is_bare(&self) -> bool {
+            self.inner.is_bare()
+        }
+
+        /// Applies the resolved settings that affect how branches are listed.
+        pub fn apply_settings(&mut self, settings: &Settings) {
+            self.include_remotes = settings.include_remotes;
+            self.sort = settings.sort;
+            self.base.clone_from(&settings.base);
+        }
+
+        /// The repository's git config, used for per-repo `githist.*` settings.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the config couldn't be read.
+        pub fn git_config(&self) -> Result<git2::Config, git2::Error> {
+            self.inner.config()?.snapshot()
+        }
+
+        /// The branch diffs and merge checks are computed against.
+        pub fn base_branch(&self) -> &str {
+            &self.base
         }
 
         /// Returns the name of the current HEAD branch, or None if detached.
@@ -57,21 +705,78 @@ pub mod branching {
             }
         }
 
-        /// Compute ahead/behind info relative to the remote tracking branch.
-        fn remote_tracking_info(
-            &self,
-            branch_name: &str,
-        ) -> Option<String> {
-            let branch = self
-                .inner
-                .find_branch(branch_name, BranchType::Local)
-                .ok()?;
-            let upstream = branch.upstream().ok()?;
-            let local_oid = branch.get().target()?;
-            let upstream_oid = upstream.get().target()?;
-            let (ahead, behind) = self.inner.graph_ahead_behind(local_oid, upstream_oid).ok()?;
+        /// The branches checked out in the repository's other worktrees, the
+        /// main one included when this is a linked one, with their paths.
+        fn other_worktrees(&self) -> HashMap<String, PathBuf> {
+            let mut checked_out = HashMap::new();
+            let mut others = Vec::new();
+            if self.inner.is_worktree() {
+                others.extend(Repository::open(self.common_dir()));
+            }
+            if let Ok(names) = self.inner.worktrees() {
+                for name in names.iter().flatten() {
+                    let Ok(worktree) = self.inner.find_worktree(name) else {
+                        continue;
+                    };
+                    // a worktree whose directory is gone can't be jumped to
+                    if worktree.validate().is_ok() {
+                        others.extend(Repository::open_from_worktree(&worktree));
+                    }
+                }
+            }
+            let here = self.inner.workdir().and_then(|dir| dir.canonicalize().ok());
+            for other in others {
+                let Some(workdir) = other.workdir().and_then(|dir| dir.canonicalize().ok()) else {
+                    continue;
+                };
+                if Some(&workdir) == here.as_ref() {
+                    continue;
+                }
+                let Ok(head) = other.head() else {
+                    continue;
+                };
+                if let Some(name) = head.shorthand().filter(|_| head.is_branch()) {
+                    checked_out.insert(name.to_string(), workdir);
+                }
+            }
+            checked_out
+        }
+
+        /// Walks HEAD's reflog and returns, for every branch name seen in a
+        /// "checkout: moving from X to Y" entry, the time it was last visited.
+        fn checkout_recency(&self) -> HashMap<String, i64> {
+            let mut visited = HashMap::new();
+            let Ok(reflog) = self.inner.reflog("HEAD") else {
+                return visited;
+            };
+            // entries are ordered newest first, so the first sighting wins
+            for entry in reflog.iter() {
+                let Some(moves) = entry
+                    .message()
+                    .and_then(|m| m.strip_prefix("checkout: moving from "))
+                else {
+                    continue;
+                };
+                let Some((from, to)) = moves.split_once(" to ") else {
+                    continue;
+                };
+                let when = entry.committer().when().seconds();
+                for name in [to, from] {
+                    visited.entry(name.to_string()).or_insert(when);
+                }
+            }
+            visited
+        }
+
+        /// Compute ahead/behind counts relative to the remote tracking branch.
+        fn ahead_behind(&self, branch_name: &str) -> Option<(usize, usize)> {
+            ahead_behind(&self.inner, branch_name)
+        }
+
+        /// Describe ahead/behind counts the way they are shown in the list.
+        fn remote_tracking_info((ahead, behind): (usize, usize)) -> String {
             if ahead == 0 && behind == 0 {
-                Some("up to date".to_string())
+                "up to date".to_string()
             } else {
                 let mut parts = Vec::new();
                 if ahead > 0 {
@@ -80,7 +785,7 @@ pub mod branching {
                 if behind > 0 {
                     parts.push(format!("-{behind}"));
                 }
-                Some(parts.join("/"))
+                parts.join("/")
             }
         }
 
@@ -88,55 +793,1199 @@ pub mod branching {
         ///
         /// Will return `git2::Error` if not a valid repo.
         pub fn get_branch_names(&self) -> Result<Vec<BranchInfo>, git2::Error> {
+            self.list_branches(true)
+        }
+
+        /// Lists branches without the fields that need a walk of their
+        /// history: `ahead_behind` is None and `merged` false until filled in
+        /// from `load_details`. An upstream that is gone is still reported.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if not a valid repo.
+        pub fn get_branch_names_fast(&self) -> Result<Vec<BranchInfo>, git2::Error> {
+            self.list_branches(false)
+        }
+
+        /// Starts computing ahead/behind, upstream status and merged state for
+        /// the local branches in `branches` on a background thread.
+        pub fn load_details(&self, branches: &[BranchInfo]) -> DetailsLoader {
+            let names: Vec<String> = branches
+                .iter()
+                .filter(|branch| branch.remote.is_none() && branch.is_branch())
+                .map(|branch| branch.branch_name.clone())
+                .collect();
+            let git_dir = self.inner.path().to_path_buf();
+            let base = self.base.clone();
+            let cache_path = self.cache_path();
+            let (sender, receiver) = mpsc::sync_channel(DETAILS_QUEUE);
+            let cancelled = Arc::new(AtomicBool::new(false));
+            let flag = Arc::clone(&cancelled);
+            let worker = thread::spawn(move || {
+                let Ok(repo) = Repository::open(&git_dir) else {
+                    return;
+                };
+                let mut cache = DetailsCache::load(cache_path);
+                let mut merge_detector = MergeDetector::new(&repo, &base, Some(&flag));
+                let mut complete = true;
+                for branch_name in names {
+                    if flag.load(Ordering::Relaxed) {
+                        complete = false;
+                        break;
+                    }
+                    // branches deleted since they were listed are skipped
+                    let Ok(branch) = repo.find_branch(&branch_name, BranchType::Local) else {
+                        continue;
+                    };
+                    let Ok(tip) = branch.get().peel_to_commit() else {
+                        continue;
+                    };
+                    let upstream_gone =
+                        branch.upstream().is_err() && has_upstream(&repo, &branch_name);
+                    let counted = upstream_tips(&branch).map(|(local, upstream)| {
+                        cache.ahead_behind(local, upstream, || {
+                            bounded_ahead_behind(&repo, local, upstream, AHEAD_BEHIND_LIMIT, &flag)
+                        })
+                    });
+                    let ahead_behind = counted.flatten();
+                    let remote_tracking = match counted {
+                        Some(None) => Some(TOO_FAR_APART.to_string()),
+                        _ => remote_tracking(ahead_behind, upstream_gone),
+                    };
+                    let merged = branch_name != base
+                        && merge_detector.as_mut().is_some_and(|detector| {
+                            let base = detector.base;
+                            // an answer cut short by cancelling isn't cached
+                            let compute = || {
+                                let merged = detector.is_merged(tip.id());
+                                (!detector.is_cancelled()).then_some(merged)
+                            };
+                            cache.merged(tip.id(), base, compute).unwrap_or_default()
+                        });
+                    if flag.load(Ordering::Relaxed) {
+                        complete = false;
+                        break;
+                    }
+                    let details = BranchDetails {
+                        branch_name,
+                        ahead_behind,
+                        remote_tracking,
+                        merged,
+                    };
+                    // the receiver is gone once the loader was dropped
+                    if sender.send(details).is_err() {
+                        complete = false;
+                        break;
+                    }
+                }
+                cache.save(complete);
+            });
+            DetailsLoader {
+                receiver,
+                cancelled,
+                worker: Some(worker),
+            }
+        }
+
+        fn list_branches(&self, details: bool) -> Result<Vec<BranchInfo>, git2::Error> {
             let mut result = Vec::new();
             let head_name = self.head_branch_name();
-            let branches = self.inner.branches(Some(BranchType::Local))?;
+            let branch_type = if self.include_remotes {
+                None
+            } else {
+                Some(BranchType::Local)
+            };
+            let branches = self.inner.branches(branch_type)?;
             let formatter = Formatter::new();
             let now = Utc::now();
+            let visited = self.checkout_recency();
+            let mut worktrees = self.other_worktrees();
+            let mut merge_detector = MergeDetector::new(&self.inner, &self.base, None);
+            // without details only what is already cached is filled in
+            let mut cache = DetailsCache::load(self.cache_path());
+            let time_ago = |timestamp: i64| relative_time(&formatter, now, timestamp);
 
             for branch in branches {
-                let (branch, _) = branch?;
-                let branch_name = branch.name()?;
-                let branch_name = branch_name.expect("no branch name!?").to_string();
-                let last_commit = branch.get().peel_to_commit()?;
+                let (branch, branch_type) = branch?;
+                // skip symbolic refs such as refs/remotes/origin/HEAD
+                if branch.get().symbolic_target().is_some() {
+                    continue;
+                }
+                // names that aren't UTF-8 are shown lossily rather than skipped
+                let branch_name = String::from_utf8_lossy(branch.name_bytes()?).into_owned();
+                // a branch pointing at something other than a commit is broken
+                let Ok(last_commit) = branch.get().peel_to_commit() else {
+                    continue;
+                };
                 let last_commit_time = last_commit.time().seconds();
-                let datetime: DateTime<Utc> =
-                    DateTime::from_timestamp(last_commit_time, 0)
-                        .expect("invalid commit timestamp");
-                let time_ago = formatter.convert_chrono(datetime, now);
-                let is_head = head_name.as_deref() == Some(branch_name.as_str());
-                let remote_tracking = self.remote_tracking_info(&branch_name);
+                let merged = branch_type == BranchType::Local
+                    && branch_name != self.base
+                    && merge_detector.as_mut().is_some_and(|detector| {
+                        let (tip, base) = (last_commit.id(), detector.base);
+                        cache
+                            .merged(tip, base, || details.then(|| detector.is_merged(tip)))
+                            .unwrap_or_default()
+                    });
+                let last_visited = visited.get(&branch_name).copied();
+                let visited_ago = last_visited.map(time_ago);
+                let time_ago = time_ago(last_commit_time);
+
+                let upstream_gone = branch_type == BranchType::Local
+                    && branch.upstream().is_err()
+                    && self.has_upstream(&branch_name);
+                let ahead_behind = match branch_type {
+                    BranchType::Local => upstream_tips(&branch).and_then(|(local, upstream)| {
+                        cache.ahead_behind(local, upstream, || {
+                            details
+                                .then(|| self.inner.graph_ahead_behind(local, upstream).ok())
+                                .flatten()
+                        })
+                    }),
+                    BranchType::Remote => None,
+                };
+                let worktree = match branch_type {
+                    BranchType::Local => worktrees.remove(&branch_name),
+                    BranchType::Remote => None,
+                };
+                let (is_head, remote_tracking, remote) = match branch_type {
+                    BranchType::Local => (
+                        head_name.as_deref() == Some(branch_name.as_str()),
+                        remote_tracking(ahead_behind, upstream_gone),
+                        None,
+                    ),
+                    BranchType::Remote => {
+                        let remote = branch
+                            .get()
+                            .name()
+                            .and_then(|refname| self.inner.branch_remote_name(refname).ok())
+                            .and_then(|buf| buf.as_str().map(String::from));
+                        (false, None, remote)
+                    }
+                };
 
                 result.push(BranchInfo {
                     branch_name,
                     last_commit_time,
                     time_ago,
+                    last_visited,
+                    visited_ago,
                     is_head,
+                    ahead_behind,
                     remote_tracking,
+                    remote,
+                    merged,
+                    upstream_gone,
+                    worktree,
+                    head_row: None,
                 });
             }
-            result.sort_by_key(|d| d.last_commit_time);
+            match self.sort {
+                // visited branches first, then the rest by commit time; HEAD wins ties
+                SortOrder::Checkout => result.sort_by_key(|d| {
+                    (d.last_visited.is_some(), d.last_visited, d.is_head, d.last_commit_time)
+                }),
+                SortOrder::Commit => result.sort_by_key(|d| d.last_commit_time),
+            }
+            if details {
+                cache.save(true);
+            }
             result.reverse();
+            if let Some(row) = self.head_row(&formatter, now) {
+                result.insert(0, row);
+            }
             Ok(result)
         }
 
+        /// A row for HEAD when it is detached or on an unborn branch, which the
+        /// branch listing can't show.
+        fn head_row(&self, formatter: &Formatter, now: DateTime<Utc>) -> Option<BranchInfo> {
+            let row = |branch_name: String, last_commit_time: i64, time_ago: String, head_row| {
+                BranchInfo {
+                    branch_name,
+                    last_commit_time,
+                    time_ago,
+                    last_visited: None,
+                    visited_ago: None,
+                    is_head: true,
+                    ahead_behind: None,
+                    remote_tracking: None,
+                    remote: None,
+                    merged: false,
+                    upstream_gone: false,
+                    worktree: None,
+                    head_row: Some(head_row),
+                }
+            };
+            match self.inner.head() {
+                Ok(head) if !head.is_branch() => {
+                    let commit = head.peel_to_commit().ok()?;
+                    let short_id = commit.as_object().short_id().ok()?;
+                    let short_id = short_id.as_str().unwrap_or_default().to_string();
+                    let description = self
+                        .inner
+                        .describe(DescribeOptions::new().describe_tags())
+                        .and_then(|describe| describe.format(None))
+                        .unwrap_or_else(|_| {
+                            String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                                .into_owned()
+                        });
+                    let time = commit.time().seconds();
+                    Some(row(
+                        format!("(HEAD detached at {short_id})"),
+                        time,
+                        relative_time(formatter, now, time),
+                        HeadRow::Detached { short_id, description },
+                    ))
+                }
+                Err(error) if error.code() == ErrorCode::UnbornBranch => {
+                    let head = self.inner.find_reference("HEAD").ok()?;
+                    let target = String::from_utf8_lossy(head.symbolic_target_bytes()?).into_owned();
+                    let name = target.strip_prefix("refs/heads/").unwrap_or(&target);
+                    Some(row(name.to_string(), 0, String::new(), HeadRow::Unborn))
+                }
+                _ => None,
+            }
+        }
+
+        /// The commit a row of the list points at.
+        fn tip_of(&self, info: &BranchInfo) -> Result<git2::Commit<'_>, git2::Error> {
+            match info.head_row {
+                Some(HeadRow::Detached { .. }) => self.inner.head()?.peel_to_commit(),
+                Some(HeadRow::Unborn) => Err(git2::Error::from_str("no commits yet")),
+                None => self.find_branch(info)?.get().peel_to_commit(),
+            }
+        }
+
+        /// The full id of the commit a row of the list points at.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the row has no commit or it can't be found.
+        pub fn tip_id(&self, info: &BranchInfo) -> Result<Oid, git2::Error> {
+            self.tip_of(info).map(|commit| commit.id())
+        }
+
+        /// Returns the local branch tracking the given remote-tracking branch,
+        /// creating it from the remote branch's tip if it doesn't exist yet.
+        ///
         /// # Errors
         ///
-        /// Will return `git2::Error` if branch change failed.
-        pub fn change_branch(&self, branch_name: &str) -> Result<(), git2::Error> {
+        /// Will return `git2::Error` if the remote branch can't be found or the
+        /// local branch couldn't be created.
+        pub fn local_branch_for_remote(
+            &self,
+            remote: &str,
+            remote_branch_name: &str,
+        ) -> Result<String, git2::Error> {
+            let local_name = remote_branch_name
+                .strip_prefix(&format!("{remote}/"))
+                .unwrap_or(remote_branch_name)
+                .to_string();
+            if self.inner.find_branch(&local_name, BranchType::Local).is_ok() {
+                return Ok(local_name);
+            }
+            let remote_branch = self
+                .inner
+                .find_branch(remote_branch_name, BranchType::Remote)?;
+            let commit = remote_branch.get().peel_to_commit()?;
+            let mut local = self.inner.branch(&local_name, &commit, false)?;
+            local.set_upstream(Some(remote_branch_name))?;
+            Ok(local_name)
+        }
+
+        /// Looks up the branch described by `info`, local or remote-tracking.
+        fn find_branch(&self, info: &BranchInfo) -> Result<Branch<'_>, git2::Error> {
+            let branch_type = if info.remote.is_some() {
+                BranchType::Remote
+            } else {
+                BranchType::Local
+            };
+            self.inner.find_branch(&info.branch_name, branch_type)
+        }
+
+        /// Walks the history of a branch from its tip, skipping the first `skip`
+        /// commits and returning at most `count`, so callers can page through
+        /// long histories lazily.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the branch can't be found or walked.
+        pub fn commit_log(
+            &self,
+            info: &BranchInfo,
+            skip: usize,
+            count: usize,
+        ) -> Result<Vec<CommitSummary>, git2::Error> {
+            let tip = self.tip_of(info)?.id();
+            let mut revwalk = self.inner.revwalk()?;
+            revwalk.push(tip)?;
+            let formatter = Formatter::new();
+            let now = Utc::now();
+
+            let mut result = Vec::new();
+            for oid in revwalk.skip(skip).take(count) {
+                let commit = self.inner.find_commit(oid?)?;
+                let short_id = commit
+                    .as_object()
+                    .short_id()?
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                result.push(CommitSummary {
+                    short_id,
+                    author: String::from_utf8_lossy(commit.author().name_bytes()).into_owned(),
+                    time_ago: relative_time(&formatter, now, commit.time().seconds()),
+                    summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                        .into_owned(),
+                });
+            }
+            Ok(result)
+        }
+
+        /// Diffs a branch against its merge-base with the base branch, returning
+        /// a diffstat followed by the unified diff.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if either branch can't be found or the
+        /// two have no common history.
+        pub fn branch_diff(&self, info: &BranchInfo) -> Result<Vec<DiffLine>, git2::Error> {
+            let tip = self.tip_of(info)?;
+            let base = self.inner.revparse_single(&self.base)?.peel_to_commit()?;
+            let merge_base = self.inner.merge_base(base.id(), tip.id())?;
+            let old_tree = self.inner.find_commit(merge_base)?.tree()?;
+            let diff = self
+                .inner
+                .diff_tree_to_tree(Some(&old_tree), Some(&tip.tree()?), None)?;
+
+            let stats = diff
+                .stats()?
+                .to_buf(DiffStatsFormat::FULL | DiffStatsFormat::INCLUDE_SUMMARY, 80)?;
+            let mut lines: Vec<DiffLine> = String::from_utf8_lossy(&stats)
+                .lines()
+                .map(|line| DiffLine {
+                    kind: DiffLineKind::Stat,
+                    text: line.to_string(),
+                })
+                .collect();
+
+            diff.print(DiffFormat::Patch, |_, _, line| {
+                let kind = match line.origin() {
+                    '+' => DiffLineKind::Added,
+                    '-' => DiffLineKind::Removed,
+                    'F' => DiffLineKind::FileHeader,
+                    'H' => DiffLineKind::HunkHeader,
+                    _ => DiffLineKind::Context,
+                };
+                let prefix = match line.origin() {
+                    origin @ ('+' | '-' | ' ') => origin.to_string(),
+                    _ => String::new(),
+                };
+                let content = String::from_utf8_lossy(line.content());
+                for text in content.lines() {
+                    lines.push(DiffLine {
+                        kind,
+                        text: format!("{prefix}{text}"),
+                    });
+                }
+                true
+            })?;
+            Ok(lines)
+        }
+
+        /// Whether the index or working tree has changes to tracked files.
+        pub fn is_dirty(&self) -> bool {
+            let mut options = StatusOptions::new();
+            options.include_untracked(false).include_ignored(false);
+            self.inner
+                .statuses(Some(&mut options))
+                .is_ok_and(|statuses| !statuses.is_empty())
+        }
+
+        fn signature(&self) -> Result<Signature<'static>, git2::Error> {
+            self.inner
+                .signature()
+                .or_else(|_| Signature::now("githist", "githist@localhost"))
+        }
+
+        fn autostash_message(branch_name: &str) -> String {
+            format!("githist autostash on {branch_name}")
+        }
+
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if branch change failed. When stashing, the
+        /// stash is restored if the checkout itself fails.
+        pub fn change_branch(
+            &mut self,
+            branch_name: &str,
+            strategy: SwitchStrategy,
+        ) -> Result<(), git2::Error> {
+            if self.is_bare() {
+                return Err(git2::Error::from_str(BARE_CHECKOUT));
+            }
             let refname = format!("refs/heads/{branch_name}");
-            let obj = self.inner.revparse_single(&refname)?;
-            self.inner.checkout_tree(&obj, None)?;
-            self.inner.set_head(&refname)?;
+            let target = self.inner.revparse_single(&refname)?.id();
+
+            let stashed = if strategy == SwitchStrategy::Stash {
+                let current = self.head_branch_name().unwrap_or_else(|| "HEAD".to_string());
+                let signature = self.signature()?;
+                self.inner
+                    .stash_save(&signature, &Self::autostash_message(&current), None)?;
+                true
+            } else {
+                false
+            };
+
+            let mut checkout = CheckoutBuilder::new();
+            if strategy == SwitchStrategy::Force {
+                checkout.force();
+            } else {
+                checkout.safe();
+            }
+            let result = self
+                .inner
+                .find_object(target, None)
+                .and_then(|obj| self.inner.checkout_tree(&obj, Some(&mut checkout)))
+                .and_then(|()| self.inner.set_head(&refname));
+            if result.is_err() && stashed {
+                self.inner.stash_pop(0, None)?;
+            }
+            result
+        }
+
+        /// Pops the stash githist made when it last left `branch_name`, if any.
+        /// Returns whether a stash was popped.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the stash couldn't be applied cleanly.
+        pub fn pop_autostash(&mut self, branch_name: &str) -> Result<bool, git2::Error> {
+            let message = Self::autostash_message(branch_name);
+            let mut found = None;
+            self.inner.stash_foreach(|index, stash_message, _| {
+                if stash_message.ends_with(&message) {
+                    found = Some(index);
+                    false
+                } else {
+                    true
+                }
+            })?;
+            match found {
+                Some(index) => {
+                    self.inner.stash_pop(index, None)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+
+        /// The revspec that resolves to the tip of the branch described by `info`.
+        pub fn branch_refname(info: &BranchInfo) -> String {
+            if let Some(HeadRow::Detached { short_id, .. }) = &info.head_row {
+                return short_id.clone();
+            }
+            match info.remote {
+                Some(_) => format!("refs/remotes/{}", info.branch_name),
+                None => format!("refs/heads/{}", info.branch_name),
+            }
+        }
+
+        /// Checks `branch_name` against git's ref-name rules.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` describing the problem if the name is invalid.
+        pub fn validate_branch_name(branch_name: &str) -> Result<(), git2::Error> {
+            if Branch::name_is_valid(branch_name)? {
+                Ok(())
+            } else {
+                Err(git2::Error::from_str(&format!(
+                    "'{branch_name}' is not a valid branch name"
+                )))
+            }
+        }
+
+        /// Creates a local branch named `branch_name` pointing at `start_point`,
+        /// which can be any revspec (`HEAD`, a branch, a SHA, `main~2`, ...).
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the name isn't a valid branch name, a
+        /// branch with that name already exists or the start point can't be resolved.
+        pub fn create_branch(&self, branch_name: &str, start_point: &str) -> Result<(), git2::Error> {
+            Self::validate_branch_name(branch_name)?;
+            let commit = self.inner.revparse_single(start_point)?.peel_to_commit()?;
+            self.inner.branch(branch_name, &commit, false)?;
+            Ok(())
+        }
+
+        /// Callbacks for talking to remotes, authenticating through the ssh agent
+        /// or the configured credential helper.
+        fn remote_callbacks<'a>(&self) -> Result<RemoteCallbacks<'a>, git2::Error> {
+            const MAX_ATTEMPTS: usize = 3;
+            let config = self.inner.config()?;
+            let mut attempts = 0;
+            let mut callbacks = RemoteCallbacks::new();
+            callbacks.credentials(move |url, username, allowed| {
+                attempts += 1;
+                if attempts > MAX_ATTEMPTS {
+                    return Err(git2::Error::from_str("authentication failed"));
+                }
+                if allowed.contains(CredentialType::SSH_KEY) {
+                    Cred::ssh_key_from_agent(username.unwrap_or("git"))
+                } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                    Cred::credential_helper(&config, url, username)
+                } else {
+                    Cred::default()
+                }
+            });
+            Ok(callbacks)
+        }
+
+        /// Pushes `refspecs` to the remote called `remote_name`.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the push failed or the remote rejected
+        /// any of the updates.
+        fn push(&self, remote_name: &str, refspecs: &[String]) -> Result<(), git2::Error> {
+            let rejected = RefCell::new(Vec::new());
+            {
+                let mut callbacks = self.remote_callbacks()?;
+                callbacks.push_update_reference(|refname, status| {
+                    if let Some(status) = status {
+                        rejected.borrow_mut().push(format!("{refname}: {status}"));
+                    }
+                    Ok(())
+                });
+                let mut options = PushOptions::new();
+                options.remote_callbacks(callbacks);
+                self.inner
+                    .find_remote(remote_name)?
+                    .push(refspecs, Some(&mut options))?;
+            }
+            let rejected = rejected.into_inner();
+            if rejected.is_empty() {
+                Ok(())
+            } else {
+                Err(git2::Error::from_str(&format!(
+                    "push rejected: {}",
+                    rejected.join(", ")
+                )))
+            }
+        }
+
+        /// Whether the local branch has an upstream configured.
+        pub fn has_upstream(&self, branch_name: &str) -> bool {
+            has_upstream(&self.inner, branch_name)
+        }
+
+        /// Renames a local branch. libgit2 moves the ref, its reflog and the
+        /// `branch.<name>.*` config section along with it. With `rename_upstream`,
+        /// the upstream branch is renamed on the remote as well, keeping its own
+        /// tip, and the local branch is set to track it under its new name.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the new name is invalid or taken, or if
+        /// renaming the remote branch failed, in which case nothing is renamed.
+        pub fn rename_branch(
+            &self,
+            old_name: &str,
+            new_name: &str,
+            rename_upstream: bool,
+        ) -> Result<(), git2::Error> {
+            Self::validate_branch_name(new_name)?;
+            let mut branch = self.inner.find_branch(old_name, BranchType::Local)?;
+            if self.inner.find_branch(new_name, BranchType::Local).is_ok() {
+                return Err(git2::Error::from_str(&format!(
+                    "a branch named '{new_name}' already exists"
+                )));
+            }
+
+            // the remote branch is recreated from its own tip, not the local one,
+            // so commits that are only on the remote aren't lost
+            let upstream = if rename_upstream {
+                let (remote, merge) = self.upstream_of(old_name)?;
+                let tracking = branch.upstream().map_err(|_| {
+                    git2::Error::from_str(&format!("the upstream of '{old_name}' is gone"))
+                })?;
+                let tracking = tracking
+                    .get()
+                    .name()
+                    .ok_or_else(|| git2::Error::from_str("upstream name isn't valid UTF-8"))?
+                    .to_string();
+                Some((remote, merge, tracking))
+            } else {
+                None
+            };
+
+            branch.rename(new_name, false)?;
+            let Some((remote, merge, tracking)) = upstream else {
+                return Ok(());
+            };
+            let pushed = self.push(
+                &remote,
+                &[format!("{tracking}:refs/heads/{new_name}"), format!(":{merge}")],
+            );
+            if let Err(error) = pushed {
+                // put the local branch back so a failed rename changes nothing
+                let mut renamed = self.inner.find_branch(new_name, BranchType::Local)?;
+                renamed.rename(old_name, false)?;
+                return Err(error);
+            }
+            let mut config = self.inner.config()?;
+            config.set_str(&format!("branch.{new_name}.remote"), &remote)?;
+            config.set_str(
+                &format!("branch.{new_name}.merge"),
+                &format!("refs/heads/{new_name}"),
+            )?;
             Ok(())
         }
 
+        /// Counts the commits reachable from a local branch but from no other
+        /// ref, which deleting the branch would leave unreachable.
+        ///
         /// # Errors
         ///
-        /// Will return `git2::Error` if branch deletion failed.
-        pub fn delete_branch(&self, branch_name: &str) -> Result<(), git2::Error> {
+        /// Will return `git2::Error` if the branch or the other refs couldn't
+        /// be read.
+        pub fn unmerged_commits(&self, branch_name: &str) -> Result<usize, git2::Error> {
+            let refname = format!("refs/heads/{branch_name}");
+            let mut revwalk = self.inner.revwalk()?;
+            revwalk.push_ref(&refname)?;
+            for reference in self.inner.references()? {
+                let reference = reference?;
+                if reference.name() == Some(refname.as_str()) {
+                    continue;
+                }
+                // tags of trees or blobs, and notes, don't keep commits alive
+                if let Ok(commit) = reference.peel_to_commit() {
+                    revwalk.hide(commit.id())?;
+                }
+            }
+            if let Ok(head) = self.inner.head() {
+                if !head.is_branch() {
+                    revwalk.hide(head.peel_to_commit()?.id())?;
+                }
+            }
+            let mut count = 0;
+            for oid in revwalk {
+                oid?;
+                count += 1;
+            }
+            Ok(count)
+        }
+
+        /// Deletes a local branch, first recording its tip and upstream in the
+        /// deletion journal under `step` so that it can be restored.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the journal couldn't be written or
+        /// branch deletion failed.
+        pub fn delete_branch(&self, branch_name: &str, step: UndoStep) -> Result<(), git2::Error> {
             let mut branch = self.inner.find_branch(branch_name, BranchType::Local)?;
-            branch.delete()
+            let (remote, merge) = self.upstream_of(branch_name).ok().unzip();
+            let record = DeletedBranch {
+                step: step.0,
+                name: branch_name.to_string(),
+                tip: branch.get().peel_to_commit()?.id().to_string(),
+                remote,
+                merge,
+                deleted_at: Utc::now().timestamp(),
+            };
+            self.append_to_journal(&record)?;
+            if let Err(error) = branch.delete() {
+                // nothing to undo after all
+                let mut records = self.deleted_branches();
+                if records.last() == Some(&record) {
+                    records.pop();
+                    self.write_journal(&records)?;
+                }
+                return Err(error);
+            }
+            Ok(())
+        }
+
+        /// The git dir shared by all worktrees.
+        fn common_dir(&self) -> PathBuf {
+            // a linked worktree's git dir points at the shared one in `commondir`
+            let git_dir = self.inner.path();
+            fs::read_to_string(git_dir.join("commondir"))
+                .map_or_else(|_| git_dir.to_path_buf(), |common| git_dir.join(common.trim()))
+        }
+
+        /// Where githist keeps its own files, shared by all worktrees.
+        fn githist_dir(&self) -> PathBuf {
+            self.common_dir().join("githist")
+        }
+
+        /// Where a new worktree for `branch_name` goes unless told otherwise:
+        /// next to the main worktree, named after it and the branch.
+        pub fn default_worktree_path(&self, branch_name: &str) -> PathBuf {
+            let common = self.common_dir();
+            let common = common.canonicalize().unwrap_or(common);
+            let main = if common.file_name().is_some_and(|name| name == ".git") {
+                common.parent().unwrap_or(&common)
+            } else {
+                &common
+            };
+            let repo_name = main
+                .file_name()
+                .map_or("worktree".into(), |name| name.to_string_lossy());
+            let repo_name = repo_name.strip_suffix(".git").unwrap_or(&repo_name);
+            let name = format!("{repo_name}-{}", branch_name.replace('/', "-"));
+            main.parent().unwrap_or(main).join(name)
+        }
+
+        /// Checks out a local branch in a new linked worktree at `path`,
+        /// relative to this worktree if it isn't absolute.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the branch is checked out already or
+        /// the worktree couldn't be created.
+        pub fn add_worktree(&self, branch_name: &str, path: &Path) -> Result<PathBuf, git2::Error> {
+            let branch = self.inner.find_branch(branch_name, BranchType::Local)?;
+            let path = match self.inner.workdir() {
+                Some(workdir) if path.is_relative() => workdir.join(path),
+                _ => path.to_path_buf(),
+            };
+            // worktree names are directories under .git/worktrees
+            let mut name = branch_name.replace('/', "-");
+            let mut suffix = 1;
+            while self.inner.find_worktree(&name).is_ok() {
+                suffix += 1;
+                name = format!("{}-{suffix}", branch_name.replace('/', "-"));
+            }
+            let mut options = WorktreeAddOptions::new();
+            options.reference(Some(branch.get()));
+            self.inner.worktree(&name, &path, Some(&options))?;
+            Ok(path)
+        }
+
+        /// Where cached branch details are kept, None with `--no-cache`.
+        fn cache_path(&self) -> Option<PathBuf> {
+            self.use_cache.then(|| self.githist_dir().join("cache"))
+        }
+
+        /// Forgets every cached ahead/behind count and merged state.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the cache exists but couldn't be removed.
+        pub fn clear_cache(&self) -> Result<(), git2::Error> {
+            match fs::remove_file(self.githist_dir().join("cache")) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(
+                    git2::Error::from_str(&format!("can't clear cache: {error}")),
+                ),
+                _ => Ok(()),
+            }
+        }
+
+        fn journal_path(&self) -> PathBuf {
+            self.githist_dir().join("deleted")
+        }
+
+        fn append_to_journal(&self, record: &DeletedBranch) -> Result<(), git2::Error> {
+            let io_error =
+                |error: std::io::Error| git2::Error::from_str(&format!("can't record deletion: {error}"));
+            let line = serde_json::to_string(record)
+                .map_err(|error| git2::Error::from_str(&error.to_string()))?;
+            fs::create_dir_all(self.githist_dir()).map_err(io_error)?;
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.journal_path())
+                .map_err(io_error)?;
+            writeln!(file, "{line}").map_err(io_error)?;
+            let records = self.deleted_branches();
+            if records.len() > JOURNAL_LIMIT {
+                self.write_journal(&records)?;
+            }
+            Ok(())
+        }
+
+        /// The deletion journal, oldest first. Lines that can't be parsed are
+        /// skipped.
+        pub fn deleted_branches(&self) -> Vec<DeletedBranch> {
+            let Ok(contents) = fs::read_to_string(self.journal_path()) else {
+                return Vec::new();
+            };
+            contents
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        }
+
+        fn write_journal(&self, records: &[DeletedBranch]) -> Result<(), git2::Error> {
+            let kept = &records[records.len().saturating_sub(JOURNAL_LIMIT)..];
+            let mut contents = String::new();
+            for record in kept {
+                let line = serde_json::to_string(record)
+                    .map_err(|error| git2::Error::from_str(&error.to_string()))?;
+                contents.push_str(&line);
+                contents.push('\n');
+            }
+            fs::write(self.journal_path(), contents)
+                .map_err(|error| git2::Error::from_str(&format!("can't update journal: {error}")))
+        }
+
+        /// Recreates the branches removed by the most recent deletion in the
+        /// journal, with their upstreams, and forgets that deletion. Returns
+        /// the names of the restored branches and, for those that couldn't be
+        /// restored, why.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if there is nothing to undo or the journal
+        /// couldn't be updated.
+        pub fn undo_deletion(&self) -> Result<Vec<Restored>, git2::Error> {
+            let mut records = self.deleted_branches();
+            let Some(last) = records.last().map(|record| record.step) else {
+                return Err(git2::Error::from_str("no deleted branches to restore"));
+            };
+            let split = records
+                .iter()
+                .rposition(|record| record.step != last)
+                .map_or(0, |index| index + 1);
+            let undone = records.split_off(split);
+            let results = undone
+                .into_iter()
+                .map(|record| {
+                    let result = self.restore_branch(&record);
+                    (record.name, result)
+                })
+                .collect();
+            // trimming also drops lines that didn't parse
+            self.write_journal(&records)?;
+            Ok(results)
+        }
+
+        fn restore_branch(&self, record: &DeletedBranch) -> Result<(), git2::Error> {
+            if self.inner.find_branch(&record.name, BranchType::Local).is_ok() {
+                return Err(git2::Error::from_str("a branch with that name exists again"));
+            }
+            let commit = Oid::from_str(&record.tip)
+                .and_then(|oid| self.inner.find_commit(oid))
+                .map_err(|_| {
+                    git2::Error::from_str(&format!("commit {} no longer exists", record.tip))
+                })?;
+            self.inner.branch(&record.name, &commit, false)?;
+            if let (Some(remote), Some(merge)) = (&record.remote, &record.merge) {
+                let mut config = self.inner.config()?;
+                config.set_str(&format!("branch.{}.remote", record.name), remote)?;
+                config.set_str(&format!("branch.{}.merge", record.name), merge)?;
+            }
+            Ok(())
+        }
+
+        /// The remote and the ref on it that `branch_name` tracks.
+        fn upstream_of(&self, branch_name: &str) -> Result<(String, String), git2::Error> {
+            let config = self.inner.config()?;
+            let remote = config.get_string(&format!("branch.{branch_name}.remote"));
+            let merge = config.get_string(&format!("branch.{branch_name}.merge"));
+            match (remote, merge) {
+                (Ok(remote), Ok(merge)) => Ok((remote, merge)),
+                _ => Err(git2::Error::from_str("no upstream configured")),
+            }
+        }
+
+        /// Pushes a local branch to its upstream. A branch without one is
+        /// pushed to `origin` under the same name and set to track it there.
+        /// Returns a short description of what was done.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if there is nowhere to push to or the push
+        /// failed or was rejected.
+        pub fn push_branch(&self, branch_name: &str) -> Result<String, git2::Error> {
+            let refname = format!("refs/heads/{branch_name}");
+            if let Ok((remote, merge)) = self.upstream_of(branch_name) {
+                self.push(&remote, &[format!("{refname}:{merge}")])?;
+                let target = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+                return Ok(format!("pushed to {remote}/{target}"));
+            }
+
+            self.inner.find_remote("origin")?;
+            self.push("origin", &[format!("{refname}:{refname}")])?;
+            let mut branch = self.inner.find_branch(branch_name, BranchType::Local)?;
+            branch.set_upstream(Some(&format!("origin/{branch_name}")))?;
+            Ok(format!("pushed to origin/{branch_name}, now tracking it"))
+        }
+
+        /// Fetches the upstream of a local branch, updating its remote-tracking
+        /// branch. Returns how the branch compares to it afterwards.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the branch has no upstream, the fetch
+        /// failed or the upstream no longer exists on the remote.
+        pub fn fetch_upstream(&self, branch_name: &str) -> Result<String, git2::Error> {
+            let (remote_name, merge) = self.upstream_of(branch_name)?;
+            let target = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+            let tracking = format!("refs/remotes/{remote_name}/{target}");
+            let refspec = format!("+{merge}:{tracking}");
+            let mut options = FetchOptions::new();
+            options.remote_callbacks(self.remote_callbacks()?);
+            let mut remote = self.inner.find_remote(&remote_name)?;
+            remote.fetch(&[refspec], Some(&mut options), None)?;
+            // fetching a ref the remote doesn't have succeeds without updating
+            // anything, so prune the stale remote-tracking branch like git would
+            let advertised = remote.list()?.iter().any(|head| head.name() == merge);
+            if !advertised {
+                if let Ok(mut stale) = self.inner.find_reference(&tracking) {
+                    stale.delete()?;
+                }
+            }
+            if self.inner.refname_to_id(&tracking).is_err() {
+                return Err(git2::Error::from_str(&format!(
+                    "the upstream {remote_name}/{target} is gone"
+                )));
+            }
+            let status = self
+                .ahead_behind(branch_name)
+                .map_or("fetched".to_string(), Self::remote_tracking_info);
+            Ok(format!("{remote_name}/{target}: {status}"))
+        }
+
+        /// Rebases a local branch onto the base branch in memory, then moves the
+        /// branch to the result. The checked out branch is only rebased when
+        /// the working tree is clean, and is checked out again afterwards.
+        /// Returns a short description of what was done.
+        ///
+        /// # Errors
+        ///
+        /// Will return `git2::Error` if the base can't be found, the working tree
+        /// is dirty, or a commit doesn't apply cleanly, in which case the branch
+        /// is left as it was.
+        pub fn rebase_onto_base(&self, branch_name: &str) -> Result<String, git2::Error> {
+            // a bare repository's HEAD has no working tree to update
+            let is_head =
+                !self.is_bare() && self.head_branch_name().as_deref() == Some(branch_name);
+            if is_head && self.is_dirty() {
+                return Err(git2::Error::from_str(
+                    "it is checked out and has local changes",
+                ));
+            }
+            // moving it would leave that worktree's files out of step with its HEAD
+            if let Some(path) = self.other_worktrees().remove(branch_name) {
+                return Err(git2::Error::from_str(&format!(
+                    "it is checked out in {}",
+                    path.display()
+                )));
+            }
+            let branch = self.inner.find_branch(branch_name, BranchType::Local)?;
+            let branch_oid = branch.get().peel_to_commit()?.id();
+            let onto_oid = self.inner.revparse_single(&self.base)?.peel_to_commit()?.id();
+            let merge_base = self.inner.merge_base(branch_oid, onto_oid)?;
+            if merge_base == onto_oid {
+                return Ok(format!("already based on {}", self.base));
+            }
+            let message = format!("githist: rebase {branch_name} onto {}", self.base);
+            if merge_base == branch_oid {
+                self.move_branch(branch_name, onto_oid, is_head, &message)?;
+                return Ok(format!("fast-forwarded to {}", self.base));
+            }
+
+            let branch_commit = self.inner.find_annotated_commit(branch_oid)?;
+            let onto_commit = self.inner.find_annotated_commit(onto_oid)?;
+            let mut options = RebaseOptions::new();
+            options.inmemory(true);
+            let mut rebase = self.inner.rebase(
+                Some(&branch_commit),
+                Some(&onto_commit),
+                None,
+                Some(&mut options),
+            )?;
+            let signature = self.signature()?;
+            let mut tip = onto_oid;
+            let mut rebased = 0;
+            while let Some(operation) = rebase.next() {
+                let operation = operation?;
+                if rebase.inmemory_index()?.has_conflicts() {
+                    rebase.abort()?;
+                    return Err(git2::Error::from_str(&format!(
+                        "{} doesn't apply cleanly",
+                        &operation.id().to_string()[..7]
+                    )));
+                }
+                match rebase.commit(None, &signature, None) {
+                    Ok(oid) => {
+                        tip = oid;
+                        rebased += 1;
+                    }
+                    // the change is already in the base
+                    Err(error) if error.code() == git2::ErrorCode::Applied => {}
+                    Err(error) => {
+                        rebase.abort()?;
+                        return Err(error);
+                    }
+                }
+            }
+            rebase.finish(Some(&signature))?;
+            self.move_branch(branch_name, tip, is_head, &message)?;
+            let commits = if rebased == 1 { "commit" } else { "commits" };
+            Ok(format!("rebased {rebased} {commits} onto {}", self.base))
+        }
+
+        /// Points a local branch at `oid`, updating the working tree to match
+        /// when it is the checked out branch.
+        fn move_branch(
+            &self,
+            branch_name: &str,
+            oid: Oid,
+            is_head: bool,
+            message: &str,
+        ) -> Result<(), git2::Error> {
+            self.inner
+                .reference(&format!("refs/heads/{branch_name}"), oid, true, message)?;
+            if is_head {
+                self.inner
+                    .checkout_head(Some(CheckoutBuilder::new().force()))?;
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{bounded_ahead_behind, Config, Repo};
+        use clap::Parser;
+        use git2::{Oid, Repository, Signature, Time};
+        use std::env;
+        use std::fs;
+        use std::path::PathBuf;
+        use std::sync::atomic::AtomicBool;
+
+        /// A repository in a fresh temporary directory, removed on drop.
+        struct TempRepo {
+            path: PathBuf,
+            repo: Repository,
+            commits: i64,
+        }
+
+        impl TempRepo {
+            fn new(name: &str) -> TempRepo {
+                let path = env::temp_dir().join(format!("githist-{name}-{}", std::process::id()));
+                let _ = fs::remove_dir_all(&path);
+                let repo = Repository::init_bare(&path).unwrap();
+                TempRepo { path, repo, commits: 0 }
+            }
+
+            /// A commit with an empty tree on top of `parents`, each one a
+            /// second younger than the last.
+            fn commit(&mut self, parents: &[Oid]) -> Oid {
+                self.commits += 1;
+                let time = Time::new(self.commits, 0);
+                let signature = Signature::new("githist", "githist@example.com", &time).unwrap();
+                let tree = self.repo.treebuilder(None).unwrap().write().unwrap();
+                let tree = self.repo.find_tree(tree).unwrap();
+                let parents: Vec<_> = parents
+                    .iter()
+                    .map(|&oid| self.repo.find_commit(oid).unwrap())
+                    .collect();
+                let parents: Vec<_> = parents.iter().collect();
+                let message = format!("commit {}", self.commits);
+                self.repo
+                    .commit(None, &signature, &signature, &message, &tree, &parents)
+                    .unwrap()
+            }
+
+            fn chain(&mut self, from: Oid, length: usize) -> Oid {
+                (0..length).fold(from, |tip, _| self.commit(&[tip]))
+            }
+
+            fn bounded(&self, local: Oid, upstream: Oid, limit: usize) -> Option<(usize, usize)> {
+                bounded_ahead_behind(&self.repo, local, upstream, limit, &AtomicBool::new(false))
+            }
+
+            fn graph(&self, local: Oid, upstream: Oid) -> (usize, usize) {
+                self.repo.graph_ahead_behind(local, upstream).unwrap()
+            }
+        }
+
+        impl Drop for TempRepo {
+            fn drop(&mut self) {
+                let _ = fs::remove_dir_all(&self.path);
+            }
+        }
+
+        #[test]
+        fn counts_diverged_branches_like_git() {
+            let mut repo = TempRepo::new("diverged");
+            let root = repo.commit(&[]);
+            let base = repo.chain(root, 5);
+            let local = repo.chain(base, 3);
+            let upstream = repo.chain(base, 4);
+            let pairs = [(local, upstream), (upstream, local), (local, base), (base, upstream), (root, local)];
+            for (a, b) in pairs {
+                assert_eq!(repo.bounded(a, b, usize::MAX), Some(repo.graph(a, b)));
+            }
+            assert_eq!(repo.bounded(local, upstream, usize::MAX), Some((3, 4)));
+            assert_eq!(repo.bounded(local, local, 0), Some((0, 0)));
+        }
+
+        #[test]
+        fn counts_merge_heavy_histories_like_git() {
+            let mut repo = TempRepo::new("merges");
+            let root = repo.commit(&[]);
+            let (mut left, mut right) = (repo.commit(&[root]), repo.commit(&[root]));
+            let mut tips = vec![root, left, right];
+            for i in 0..30 {
+                left = repo.commit(&[left]);
+                right = repo.commit(&[right]);
+                if i % 3 == 0 {
+                    left = repo.commit(&[left, right]);
+                }
+                if i % 7 == 0 {
+                    let side = repo.chain(left, 2);
+                    right = repo.commit(&[right, side]);
+                }
+                tips.extend([left, right]);
+            }
+            for &a in &tips {
+                for &b in &tips {
+                    assert_eq!(repo.bounded(a, b, usize::MAX), Some(repo.graph(a, b)));
+                }
+            }
+        }
+
+        #[test]
+        fn gives_up_past_the_limit() {
+            let mut repo = TempRepo::new("limit");
+            let root = repo.commit(&[]);
+            let local = repo.chain(root, 10);
+            let upstream = repo.chain(root, 10);
+            assert_eq!(repo.bounded(local, upstream, usize::MAX), Some((10, 10)));
+            // the twenty commits on either side, after which only the shared root is left
+            assert_eq!(repo.bounded(local, upstream, 20), Some((10, 10)));
+            assert_eq!(repo.bounded(local, upstream, 19), None);
+        }
+
+        #[test]
+        fn open_stops_at_ceiling_directories() {
+            let repo = TempRepo::new("ceiling");
+            let ceiling = repo.path.join("x");
+            let inside = ceiling.join("y");
+            fs::create_dir_all(&inside).unwrap();
+            let config = Config::parse_from(["githist", inside.to_str().unwrap()]);
+            assert!(Repo::open(&config).is_ok());
+            env::set_var("GIT_CEILING_DIRECTORIES", &ceiling);
+            let opened = Repo::open(&config);
+            env::remove_var("GIT_CEILING_DIRECTORIES");
+            assert!(opened.is_err());
+        }
+
+        #[test]
+        fn gives_up_when_cancelled() {
+            let mut repo = TempRepo::new("cancelled");
+            let root = repo.commit(&[]);
+            let local = repo.chain(root, 3);
+            let cancelled = AtomicBool::new(true);
+            assert_eq!(bounded_ahead_behind(&repo.repo, local, root, usize::MAX, &cancelled), None);
         }
     }
 }
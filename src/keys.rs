@@ -0,0 +1,715 @@
+pub mod bindings {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use serde::Deserialize;
+    use std::fmt;
+
+    /// Everything that can be bound to a key in the branch list.
+    #[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum Action {
+        Help,
+        Quit,
+        Switch,
+        Delete,
+        Undo,
+        Filter,
+        ShrinkFilter,
+        Down,
+        Up,
+        PageDown,
+        PageUp,
+        First,
+        Last,
+        Unselect,
+        NewBranch,
+        Rename,
+        AddWorktree,
+        CleanupMerged,
+        CleanupGone,
+        GoneOnly,
+        Mark,
+        MarkRange,
+        MarkAll,
+        Push,
+        Fetch,
+        Rebase,
+        TogglePreview,
+        PreviewMode,
+        PreviewDown,
+        PreviewUp,
+    }
+
+    /// An action of one of the TUI's modes, with the keys it is bound to
+    /// unless the config file says otherwise.
+    pub trait Bindable: Copy + PartialEq + 'static {
+        /// Every action, in the order the help lists them.
+        const ALL: &'static [Self];
+
+        /// A short description for the help.
+        fn description(self) -> &'static str;
+
+        fn default_keys(self) -> &'static [&'static str];
+    }
+
+    impl Action {
+        /// The name used for the action in the config file.
+        pub fn name(self) -> &'static str {
+            match self {
+                Action::Help => "help",
+                Action::Quit => "quit",
+                Action::Switch => "switch",
+                Action::Delete => "delete",
+                Action::Undo => "undo",
+                Action::Filter => "filter",
+                Action::ShrinkFilter => "shrink-filter",
+                Action::Down => "down",
+                Action::Up => "up",
+                Action::PageDown => "page-down",
+                Action::PageUp => "page-up",
+                Action::First => "first",
+                Action::Last => "last",
+                Action::Unselect => "unselect",
+                Action::NewBranch => "new-branch",
+                Action::Rename => "rename",
+                Action::AddWorktree => "add-worktree",
+                Action::CleanupMerged => "cleanup-merged",
+                Action::CleanupGone => "cleanup-gone",
+                Action::GoneOnly => "gone-only",
+                Action::Mark => "mark",
+                Action::MarkRange => "mark-range",
+                Action::MarkAll => "mark-all",
+                Action::Push => "push",
+                Action::Fetch => "fetch",
+                Action::Rebase => "rebase",
+                Action::TogglePreview => "toggle-preview",
+                Action::PreviewMode => "preview-mode",
+                Action::PreviewDown => "preview-down",
+                Action::PreviewUp => "preview-up",
+            }
+        }
+    }
+
+    impl Bindable for Action {
+        const ALL: &'static [Action] = &[
+            Action::Help,
+            Action::Quit,
+            Action::Switch,
+            Action::Delete,
+            Action::Undo,
+            Action::Filter,
+            Action::ShrinkFilter,
+            Action::Down,
+            Action::Up,
+            Action::PageDown,
+            Action::PageUp,
+            Action::First,
+            Action::Last,
+            Action::Unselect,
+            Action::NewBranch,
+            Action::Rename,
+            Action::AddWorktree,
+            Action::CleanupMerged,
+            Action::CleanupGone,
+            Action::GoneOnly,
+            Action::Mark,
+            Action::MarkRange,
+            Action::MarkAll,
+            Action::Push,
+            Action::Fetch,
+            Action::Rebase,
+            Action::TogglePreview,
+            Action::PreviewMode,
+            Action::PreviewDown,
+            Action::PreviewUp,
+        ];
+
+        fn description(self) -> &'static str {
+            match self {
+                Action::Help => "help",
+                Action::Quit => "quit",
+                Action::Switch => "switch branch",
+                Action::Delete => "delete",
+                Action::Undo => "undo delete",
+                Action::Filter => "filter",
+                Action::ShrinkFilter => "shrink filter",
+                Action::Down => "down",
+                Action::Up => "up",
+                Action::PageDown => "page down",
+                Action::PageUp => "page up",
+                Action::First => "first",
+                Action::Last => "last",
+                Action::Unselect => "unselect",
+                Action::NewBranch => "new branch",
+                Action::Rename => "rename",
+                Action::AddWorktree => "new worktree",
+                Action::CleanupMerged => "clean up merged",
+                Action::CleanupGone => "clean up gone",
+                Action::GoneOnly => "gone only",
+                Action::Mark => "mark",
+                Action::MarkRange => "mark range",
+                Action::MarkAll => "mark all",
+                Action::Push => "push",
+                Action::Fetch => "fetch upstream",
+                Action::Rebase => "rebase onto base",
+                Action::TogglePreview => "preview",
+                Action::PreviewMode => "log/diff",
+                Action::PreviewDown => "scroll preview down",
+                Action::PreviewUp => "scroll preview up",
+            }
+        }
+
+        fn default_keys(self) -> &'static [&'static str] {
+            match self {
+                Action::Help => &["?"],
+                Action::Quit => &["q", "Q", "esc"],
+                Action::Switch => &["enter"],
+                Action::Delete => &["D"],
+                Action::Undo => &["u"],
+                Action::Filter => &["/"],
+                Action::ShrinkFilter => &["backspace"],
+                Action::Down => &["j", "down"],
+                Action::Up => &["k", "up"],
+                Action::PageDown => &["pagedown"],
+                Action::PageUp => &["pageup"],
+                Action::First => &["g", "home"],
+                Action::Last => &["G", "end"],
+                Action::Unselect => &["left"],
+                Action::NewBranch => &["n"],
+                Action::Rename => &["r"],
+                Action::AddWorktree => &["w"],
+                Action::CleanupMerged => &["M"],
+                Action::CleanupGone => &["X"],
+                Action::GoneOnly => &["o"],
+                Action::Mark => &["space"],
+                Action::MarkRange => &["v"],
+                Action::MarkAll => &["*"],
+                Action::Push => &["P"],
+                Action::Fetch => &["F"],
+                Action::Rebase => &["R"],
+                Action::TogglePreview => &["p"],
+                Action::PreviewMode => &["tab"],
+                Action::PreviewDown => &["J"],
+                Action::PreviewUp => &["K"],
+            }
+        }
+    }
+
+    /// Keys while typing a filter.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum FilterAction {
+        Done,
+        DeleteChar,
+    }
+
+    impl Bindable for FilterAction {
+        const ALL: &'static [FilterAction] = &[FilterAction::Done, FilterAction::DeleteChar];
+
+        fn description(self) -> &'static str {
+            match self {
+                FilterAction::Done => "stop editing the filter",
+                FilterAction::DeleteChar => "delete a character, stopping when empty",
+            }
+        }
+
+        fn default_keys(self) -> &'static [&'static str] {
+            match self {
+                FilterAction::Done => &["enter", "esc"],
+                FilterAction::DeleteChar => &["backspace"],
+            }
+        }
+    }
+
+    /// Keys on the cleanup screen.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum CleanupAction {
+        Down,
+        Up,
+        Toggle,
+        ToggleAll,
+        Run,
+        Back,
+    }
+
+    impl Bindable for CleanupAction {
+        const ALL: &'static [CleanupAction] = &[
+            CleanupAction::Down,
+            CleanupAction::Up,
+            CleanupAction::Toggle,
+            CleanupAction::ToggleAll,
+            CleanupAction::Run,
+            CleanupAction::Back,
+        ];
+
+        fn description(self) -> &'static str {
+            match self {
+                CleanupAction::Down => "down",
+                CleanupAction::Up => "up",
+                CleanupAction::Toggle => "check or uncheck a branch",
+                CleanupAction::ToggleAll => "check or uncheck all",
+                CleanupAction::Run => "run on the checked branches",
+                CleanupAction::Back => "back to the branch list",
+            }
+        }
+
+        fn default_keys(self) -> &'static [&'static str] {
+            match self {
+                CleanupAction::Down => &["j", "down"],
+                CleanupAction::Up => &["k", "up"],
+                CleanupAction::Toggle => &["space"],
+                CleanupAction::ToggleAll => &["a"],
+                CleanupAction::Run => &["enter"],
+                CleanupAction::Back => &["esc", "q"],
+            }
+        }
+    }
+
+    /// Keys answering a confirmation in the status bar.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ConfirmAction {
+        Yes,
+        No,
+        Stash,
+        Carry,
+        Force,
+        Print,
+    }
+
+    impl Bindable for ConfirmAction {
+        const ALL: &'static [ConfirmAction] = &[
+            ConfirmAction::Yes,
+            ConfirmAction::No,
+            ConfirmAction::Stash,
+            ConfirmAction::Carry,
+            ConfirmAction::Force,
+            ConfirmAction::Print,
+        ];
+
+        fn description(self) -> &'static str {
+            match self {
+                ConfirmAction::Yes => "confirm",
+                ConfirmAction::No => "cancel",
+                ConfirmAction::Stash => "with local changes: stash them and switch",
+                ConfirmAction::Carry => "with local changes: carry them over",
+                ConfirmAction::Force => "with local changes: discard them and switch",
+                ConfirmAction::Print => "in another worktree: print its path",
+            }
+        }
+
+        fn default_keys(self) -> &'static [&'static str] {
+            match self {
+                ConfirmAction::Yes => &["y", "Y"],
+                ConfirmAction::No => &["n", "N", "esc", "backspace"],
+                ConfirmAction::Stash => &["s", "S"],
+                ConfirmAction::Carry => &["c", "C"],
+                ConfirmAction::Force => &["f", "F"],
+                ConfirmAction::Print => &["p", "P"],
+            }
+        }
+    }
+
+    /// Keys while typing into a prompt: a new branch's name or start point,
+    /// a rename, a worktree path, or a branch name to confirm deleting it.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PromptAction {
+        Accept,
+        Cancel,
+        DeleteChar,
+    }
+
+    impl Bindable for PromptAction {
+        const ALL: &'static [PromptAction] = &[
+            PromptAction::Accept,
+            PromptAction::Cancel,
+            PromptAction::DeleteChar,
+        ];
+
+        fn description(self) -> &'static str {
+            match self {
+                PromptAction::Accept => "accept",
+                PromptAction::Cancel => "cancel",
+                PromptAction::DeleteChar => "delete a character",
+            }
+        }
+
+        fn default_keys(self) -> &'static [&'static str] {
+            match self {
+                PromptAction::Accept => &["enter"],
+                PromptAction::Cancel => &["esc"],
+                PromptAction::DeleteChar => &["backspace"],
+            }
+        }
+    }
+
+    /// Keys choosing where a new branch starts.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum StartPointAction {
+        Head,
+        Highlighted,
+        Revspec,
+    }
+
+    impl Bindable for StartPointAction {
+        const ALL: &'static [StartPointAction] = &[
+            StartPointAction::Head,
+            StartPointAction::Highlighted,
+            StartPointAction::Revspec,
+        ];
+
+        fn description(self) -> &'static str {
+            match self {
+                StartPointAction::Head => "HEAD",
+                StartPointAction::Highlighted => "the highlighted branch",
+                StartPointAction::Revspec => "a revspec to type",
+            }
+        }
+
+        fn default_keys(self) -> &'static [&'static str] {
+            match self {
+                StartPointAction::Head => &["h", "H"],
+                StartPointAction::Highlighted => &["b", "B"],
+                StartPointAction::Revspec => &["r", "R"],
+            }
+        }
+    }
+
+    /// Keys in the help overlay.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum HelpAction {
+        Close,
+        Down,
+        Up,
+        PageDown,
+        PageUp,
+        First,
+        Last,
+    }
+
+    impl Bindable for HelpAction {
+        const ALL: &'static [HelpAction] = &[
+            HelpAction::Close,
+            HelpAction::Down,
+            HelpAction::Up,
+            HelpAction::PageDown,
+            HelpAction::PageUp,
+            HelpAction::First,
+            HelpAction::Last,
+        ];
+
+        fn description(self) -> &'static str {
+            match self {
+                HelpAction::Close => "close the help",
+                HelpAction::Down => "scroll down",
+                HelpAction::Up => "scroll up",
+                HelpAction::PageDown => "page down",
+                HelpAction::PageUp => "page up",
+                HelpAction::First => "top",
+                HelpAction::Last => "bottom",
+            }
+        }
+
+        fn default_keys(self) -> &'static [&'static str] {
+            match self {
+                HelpAction::Close => &["?", "esc", "q"],
+                HelpAction::Down => &["j", "down"],
+                HelpAction::Up => &["k", "up"],
+                HelpAction::PageDown => &["pagedown", "space"],
+                HelpAction::PageUp => &["pageup"],
+                HelpAction::First => &["g", "home"],
+                HelpAction::Last => &["G", "end"],
+            }
+        }
+    }
+
+    /// A key press, with Shift folded into the character for printable keys.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct KeyBinding {
+        pub code: KeyCode,
+        pub modifiers: KeyModifiers,
+    }
+
+    impl KeyBinding {
+        /// Parses keys like `j`, `G`, `enter`, `ctrl-n`, `alt-pagedown` or `space`.
+        pub fn parse(key: &str) -> Option<KeyBinding> {
+            let mut modifiers = KeyModifiers::NONE;
+            let mut rest = key;
+            loop {
+                let lower = rest.to_lowercase();
+                let (modifier, len) = if lower.starts_with("ctrl-") {
+                    (KeyModifiers::CONTROL, 5)
+                } else if lower.starts_with("alt-") {
+                    (KeyModifiers::ALT, 4)
+                } else if lower.starts_with("shift-") {
+                    (KeyModifiers::SHIFT, 6)
+                } else {
+                    break;
+                };
+                modifiers |= modifier;
+                rest = &rest[len..];
+            }
+
+            let mut chars = rest.chars();
+            let code = match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => match rest.to_lowercase().as_str() {
+                    "enter" | "return" => KeyCode::Enter,
+                    "esc" | "escape" => KeyCode::Esc,
+                    "backspace" => KeyCode::Backspace,
+                    "tab" => KeyCode::Tab,
+                    "space" => KeyCode::Char(' '),
+                    "up" => KeyCode::Up,
+                    "down" => KeyCode::Down,
+                    "left" => KeyCode::Left,
+                    "right" => KeyCode::Right,
+                    "home" => KeyCode::Home,
+                    "end" => KeyCode::End,
+                    "pageup" | "pgup" => KeyCode::PageUp,
+                    "pagedown" | "pgdown" | "pgdn" => KeyCode::PageDown,
+                    "delete" | "del" => KeyCode::Delete,
+                    "insert" | "ins" => KeyCode::Insert,
+                    name => {
+                        let number = name.strip_prefix('f')?.parse().ok()?;
+                        KeyCode::F(number)
+                    }
+                },
+            };
+            Some(KeyBinding::from(KeyEvent::new(code, modifiers)))
+        }
+    }
+
+    impl From<KeyEvent> for KeyBinding {
+        fn from(key: KeyEvent) -> Self {
+            match key.code {
+                KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::SHIFT) => KeyBinding {
+                    code: KeyCode::Char(c.to_ascii_uppercase()),
+                    modifiers: key.modifiers - KeyModifiers::SHIFT,
+                },
+                code => KeyBinding {
+                    code,
+                    modifiers: key.modifiers,
+                },
+            }
+        }
+    }
+
+    impl fmt::Display for KeyBinding {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            if self.modifiers.contains(KeyModifiers::CONTROL) {
+                write!(f, "Ctrl+")?;
+            }
+            if self.modifiers.contains(KeyModifiers::ALT) {
+                write!(f, "Alt+")?;
+            }
+            if self.modifiers.contains(KeyModifiers::SHIFT) {
+                write!(f, "Shift+")?;
+            }
+            match self.code {
+                KeyCode::Char(' ') => write!(f, "Space"),
+                KeyCode::Char(c) => write!(f, "{c}"),
+                KeyCode::Enter => write!(f, "↩"),
+                KeyCode::Esc => write!(f, "Esc"),
+                KeyCode::Backspace => write!(f, "Backspace"),
+                KeyCode::Tab => write!(f, "Tab"),
+                KeyCode::Up => write!(f, "↑"),
+                KeyCode::Down => write!(f, "↓"),
+                KeyCode::Left => write!(f, "←"),
+                KeyCode::Right => write!(f, "→"),
+                KeyCode::Home => write!(f, "Home"),
+                KeyCode::End => write!(f, "End"),
+                KeyCode::PageUp => write!(f, "PgUp"),
+                KeyCode::PageDown => write!(f, "PgDn"),
+                KeyCode::Delete => write!(f, "Del"),
+                KeyCode::Insert => write!(f, "Ins"),
+                KeyCode::F(n) => write!(f, "F{n}"),
+                other => write!(f, "{other:?}"),
+            }
+        }
+    }
+
+    /// The keys bound to each action of one mode.
+    #[derive(Debug, Clone)]
+    pub struct KeyMap<A> {
+        bindings: Vec<(KeyBinding, A)>,
+    }
+
+    impl<A: Bindable> Default for KeyMap<A> {
+        fn default() -> Self {
+            let bindings = A::ALL
+                .iter()
+                .flat_map(|action| {
+                    action.default_keys().iter().map(move |key| {
+                        let binding = KeyBinding::parse(key).expect("invalid default key binding");
+                        (binding, *action)
+                    })
+                })
+                .collect();
+            KeyMap { bindings }
+        }
+    }
+
+    impl<A: Bindable> KeyMap<A> {
+        pub fn action_for(&self, key: KeyEvent) -> Option<A> {
+            let binding = KeyBinding::from(key);
+            self.bindings
+                .iter()
+                .find(|(b, _)| *b == binding)
+                .map(|(_, action)| *action)
+        }
+
+        pub fn keys_for(&self, action: A) -> impl Iterator<Item = KeyBinding> + '_ {
+            self.bindings
+                .iter()
+                .filter(move |(_, a)| *a == action)
+                .map(|(binding, _)| *binding)
+        }
+
+        /// Replaces the keys bound to `action`; an empty list unbinds it.
+        /// A key that was bound to another action moves to `action`.
+        pub fn rebind(&mut self, action: A, keys: &[KeyBinding]) {
+            self.bindings
+                .retain(|(binding, a)| *a != action && !keys.contains(binding));
+            self.bindings.extend(keys.iter().map(|key| (*key, action)));
+        }
+
+        /// The keys for `action` joined for display, like `j/↓`.
+        pub fn describe_keys(&self, action: A) -> String {
+            self.keys_for(action)
+                .map(|key| key.to_string())
+                .collect::<Vec<_>>()
+                .join("/")
+        }
+
+        /// The bound actions as (keys, description) pairs, in help order.
+        pub fn entries(&self) -> Vec<(String, &'static str)> {
+            A::ALL
+                .iter()
+                .map(|action| (self.describe_keys(*action), action.description()))
+                .filter(|(keys, _)| !keys.is_empty())
+                .collect()
+        }
+
+        /// The one-line summary of the bound actions.
+        pub fn help_line(&self) -> String {
+            self.summary(A::ALL)
+        }
+
+        /// The one-line summary of just `actions`, skipping unbound ones.
+        pub fn summary(&self, actions: &[A]) -> String {
+            actions
+                .iter()
+                .map(|action| (self.describe_keys(*action), action.description()))
+                .filter(|(keys, _)| !keys.is_empty())
+                .map(|(keys, description)| format!("{keys}: {description}"))
+                .collect::<Vec<_>>()
+                .join(" | ")
+        }
+    }
+
+    /// The key maps of every mode. Only the branch list's is configurable.
+    #[derive(Debug, Clone, Default)]
+    pub struct KeyMaps {
+        pub normal: KeyMap<Action>,
+        pub filter: KeyMap<FilterAction>,
+        pub cleanup: KeyMap<CleanupAction>,
+        pub confirm: KeyMap<ConfirmAction>,
+        pub prompt: KeyMap<PromptAction>,
+        pub start_point: KeyMap<StartPointAction>,
+        pub help: KeyMap<HelpAction>,
+    }
+
+    impl KeyMaps {
+        /// The sections of the help overlay: a title per mode and the
+        /// (keys, description) pairs bound in it.
+        pub fn help_sections(&self) -> Vec<(&'static str, Vec<(String, &'static str)>)> {
+            let mut normal = self.normal.entries();
+            normal.push(("other characters".to_string(), "start filtering"));
+            let mut filter = self.filter.entries();
+            filter.push(("other characters".to_string(), "add to the filter"));
+            let mut prompt = self.prompt.entries();
+            prompt.push(("other characters".to_string(), "type"));
+            vec![
+                ("branch list", normal),
+                ("filter", filter),
+                ("prompts", prompt),
+                ("new branch start point", self.start_point.entries()),
+                ("confirmations", self.confirm.entries()),
+                ("cleanup screen", self.cleanup.entries()),
+                ("help", self.help.entries()),
+            ]
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::KeyBinding;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+        fn key(code: KeyCode, modifiers: KeyModifiers) -> Option<KeyBinding> {
+            Some(KeyBinding { code, modifiers })
+        }
+
+        fn plain(code: KeyCode) -> Option<KeyBinding> {
+            key(code, KeyModifiers::NONE)
+        }
+
+        #[test]
+        fn parses_single_characters() {
+            assert_eq!(KeyBinding::parse("j"), plain(KeyCode::Char('j')));
+            assert_eq!(KeyBinding::parse("G"), plain(KeyCode::Char('G')));
+            assert_eq!(KeyBinding::parse("-"), plain(KeyCode::Char('-')));
+            assert_eq!(KeyBinding::parse("f"), plain(KeyCode::Char('f')));
+        }
+
+        #[test]
+        fn parses_named_keys_in_any_case() {
+            assert_eq!(KeyBinding::parse("enter"), plain(KeyCode::Enter));
+            assert_eq!(KeyBinding::parse("Esc"), plain(KeyCode::Esc));
+            assert_eq!(KeyBinding::parse("space"), plain(KeyCode::Char(' ')));
+            assert_eq!(KeyBinding::parse("PgDn"), plain(KeyCode::PageDown));
+            assert_eq!(KeyBinding::parse("f5"), plain(KeyCode::F(5)));
+            assert_eq!(KeyBinding::parse("F12"), plain(KeyCode::F(12)));
+        }
+
+        #[test]
+        fn parses_modifiers() {
+            assert_eq!(
+                KeyBinding::parse("ctrl-n"),
+                key(KeyCode::Char('n'), KeyModifiers::CONTROL)
+            );
+            assert_eq!(
+                KeyBinding::parse("Alt-pagedown"),
+                key(KeyCode::PageDown, KeyModifiers::ALT)
+            );
+            assert_eq!(
+                KeyBinding::parse("ctrl-alt-x"),
+                key(
+                    KeyCode::Char('x'),
+                    KeyModifiers::CONTROL | KeyModifiers::ALT
+                )
+            );
+            assert_eq!(
+                KeyBinding::parse("shift-tab"),
+                key(KeyCode::Tab, KeyModifiers::SHIFT)
+            );
+        }
+
+        #[test]
+        fn folds_shift_into_characters() {
+            assert_eq!(KeyBinding::parse("shift-g"), KeyBinding::parse("G"));
+            assert_eq!(
+                KeyBinding::parse("ctrl-shift-g"),
+                key(KeyCode::Char('G'), KeyModifiers::CONTROL)
+            );
+            let pressed = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+            assert_eq!(Some(KeyBinding::from(pressed)), KeyBinding::parse("G"));
+        }
+
+        #[test]
+        fn rejects_unknown_keys() {
+            assert_eq!(KeyBinding::parse(""), None);
+            assert_eq!(KeyBinding::parse("ctrl-"), None);
+            assert_eq!(KeyBinding::parse("jj"), None);
+            assert_eq!(KeyBinding::parse("fx"), None);
+            assert_eq!(KeyBinding::parse("hyper-x"), None);
+        }
+    }
+}
@@ -1,36 +1,413 @@
-use crate::git::branching::BranchInfo;
-use ratatui::backend::CrosstermBackend;
+use crate::git::branching::{BranchInfo, CommitSummary, DetailsLoader, DiffLine, Repo};
+use crate::fuzzy::matcher::fuzzy_match;
+use crate::settings::config::Settings;
+use crate::keys::bindings::ConfirmAction;
+use crate::ui::gui::Tui;
+use ratatui::layout::Rect;
 use ratatui::widgets::ListState;
-use ratatui::Terminal;
-use std::io::Stdout;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::TryRecvError;
+use std::ops::Range;
+use std::time::Instant;
 
+pub mod fuzzy;
 pub mod git;
+pub mod keys;
+pub mod output;
+pub mod settings;
+pub mod theme;
 pub mod ui;
 
 pub struct StatefulList {
     pub state: ListState,
     pub items: Vec<BranchInfo>,
     pub filtered: Option<Box<Vec<BranchInfo>>>,
+    /// Char indices of the filter matches in each filtered branch name.
+    pub match_positions: Vec<Vec<usize>>,
+    /// Names of the branches marked for a batch action.
+    pub marked: HashSet<String>,
+    /// The branch last marked or unmarked on its own, where a range starts.
+    pub mark_anchor: Option<String>,
+    /// Where each local branch is in `items` and in `filtered`, so details
+    /// can be applied as they arrive without searching the lists.
+    item_rows: HashMap<String, usize>,
+    filtered_rows: HashMap<String, usize>,
+}
+
+/// The index of every local branch in `branches`, by name.
+fn local_rows(branches: &[BranchInfo]) -> HashMap<String, usize> {
+    branches
+        .iter()
+        .enumerate()
+        .filter(|(_, branch)| branch.remote.is_none())
+        .map(|(index, branch)| (branch.branch_name.clone(), index))
+        .collect()
+}
+
+/// Number of commits fetched at a time for the log preview.
+const PREVIEW_PAGE_SIZE: usize = 50;
+
+/// The commit log of the highlighted branch, loaded a page at a time.
+pub struct LogPreview {
+    pub branch_name: String,
+    pub commits: Vec<CommitSummary>,
+    pub scroll: usize,
+    pub exhausted: bool,
+    pub error: Option<String>,
+}
+
+/// The diff of the highlighted branch against its merge-base with the base branch.
+pub struct DiffPreview {
+    pub branch_name: String,
+    pub base: String,
+    pub lines: Vec<DiffLine>,
+    pub scroll: usize,
+    pub error: Option<String>,
+}
+
+/// What the preview pane shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewMode {
+    Log,
+    Diff,
+}
+
+/// Where the "new branch" command is in its sequence of prompts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NewBranchStep {
+    /// Typing the name of the new branch.
+    Name,
+    /// Choosing what the new branch starts from.
+    StartPoint { name: String },
+    /// Typing a revspec for the new branch to start from.
+    Revspec { name: String },
+    /// The branch was created; asking whether to check it out.
+    Checkout { name: String },
+}
+
+pub struct NewBranchPrompt {
+    pub step: NewBranchStep,
+    pub input: String,
+    /// The branch highlighted when the command started, offered as a start point.
+    pub highlighted: Option<BranchInfo>,
+}
+
+/// The inline editor for renaming the highlighted branch.
+pub struct RenamePrompt {
+    pub branch_name: String,
+    pub input: String,
+    /// Set once the new name is entered for a branch with an upstream, while
+    /// asking whether to rename the remote branch too.
+    pub confirm_remote: bool,
+}
+
+/// The path editor for checking out the highlighted branch in a new worktree.
+pub struct WorktreePrompt {
+    pub branch_name: String,
+    pub input: String,
+}
+
+/// Asking whether to delete the highlighted branch.
+pub struct DeleteConfirmation {
+    pub branch_name: String,
+    /// Commits that deleting the branch would leave unreachable, counted as
+    /// none when the branch is merged into the base branch by other means.
+    pub unmerged_commits: usize,
+    /// The branch name typed so far, when it must be typed to confirm.
+    pub input: String,
+}
+
+impl DeleteConfirmation {
+    /// Whether the branch name must be typed instead of answering y/n, like
+    /// `git branch -D` instead of `-d`.
+    #[must_use]
+    pub fn needs_typing(&self) -> bool {
+        self.unmerged_commits > 0
+    }
+}
+
+/// "1 commit", "3 commits".
+#[must_use]
+pub fn commits(count: usize) -> String {
+    if count == 1 {
+        "1 commit".to_string()
+    } else {
+        format!("{count} commits")
+    }
+}
+
+/// What a checklist of branches does to the checked ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOperation {
+    Delete,
+    Push,
+    Fetch,
+    Rebase,
+}
+
+impl BatchOperation {
+    #[must_use]
+    pub fn verb(self) -> &'static str {
+        match self {
+            BatchOperation::Delete => "delete",
+            BatchOperation::Push => "push",
+            BatchOperation::Fetch => "fetch upstream of",
+            BatchOperation::Rebase => "rebase",
+        }
+    }
+}
+
+/// A checklist of branches to run a batch operation on, such as deleting
+/// those already merged into the base branch, all checked to begin with.
+pub struct CleanupScreen {
+    pub title: String,
+    pub operation: BatchOperation,
+    pub branches: Vec<String>,
+    pub checked: Vec<bool>,
+    pub state: ListState,
+    /// Set while asking for confirmation to run the operation.
+    pub confirming: bool,
+    /// Commits each branch would leave unreachable when deleted, see
+    /// `DeleteConfirmation::unmerged_commits`.
+    pub unmerged_commits: Vec<usize>,
+    /// Branches with unmerged commits whose names have been typed to confirm.
+    pub typed: Vec<bool>,
+    /// The branch name being typed.
+    pub input: String,
+}
+
+/// The outcome of a batch operation for each branch it ran on, shown in the
+/// status area until the next key press.
+pub struct BatchReport {
+    pub title: String,
+    pub results: Vec<(String, Result<String, String>)>,
+}
+
+impl CleanupScreen {
+    #[must_use]
+    pub fn new(title: String, branches: Vec<String>, operation: BatchOperation) -> CleanupScreen {
+        let mut state = ListState::default();
+        if !branches.is_empty() {
+            state.select(Some(0));
+        }
+        CleanupScreen {
+            title,
+            operation,
+            checked: vec![true; branches.len()],
+            unmerged_commits: vec![0; branches.len()],
+            typed: vec![false; branches.len()],
+            branches,
+            state,
+            confirming: false,
+            input: String::new(),
+        }
+    }
+
+    /// The first checked branch with unmerged commits whose name hasn't been
+    /// typed yet, with the number of those commits.
+    #[must_use]
+    pub fn next_to_type(&self) -> Option<(&str, usize)> {
+        (0..self.branches.len())
+            .find(|&i| self.checked[i] && self.unmerged_commits[i] > 0 && !self.typed[i])
+            .map(|i| (self.branches[i].as_str(), self.unmerged_commits[i]))
+    }
+
+    /// Leaves the confirmation, forgetting the names typed for it.
+    pub fn cancel_confirming(&mut self) {
+        self.confirming = false;
+        self.typed.fill(false);
+        self.input.clear();
+    }
+
+    pub fn next(&mut self) {
+        if self.branches.is_empty() {
+            return;
+        }
+        let i = self.state.selected().map_or(0, |i| (i + 1) % self.branches.len());
+        self.state.select(Some(i));
+    }
+
+    pub fn previous(&mut self) {
+        if self.branches.is_empty() {
+            return;
+        }
+        let i = self
+            .state
+            .selected()
+            .map_or(0, |i| i.checked_sub(1).unwrap_or(self.branches.len() - 1));
+        self.state.select(Some(i));
+    }
+
+    pub fn toggle_selected(&mut self) {
+        if let Some(checked) = self.state.selected().and_then(|i| self.checked.get_mut(i)) {
+            *checked = !*checked;
+        }
+    }
+
+    /// Checks every branch, or unchecks them all if they all were checked.
+    pub fn toggle_all(&mut self) {
+        let all = self.checked.iter().all(|c| *c);
+        self.checked.fill(!all);
+    }
+
+    pub fn checked_branches(&self) -> Vec<String> {
+        self.branches
+            .iter()
+            .zip(&self.checked)
+            .filter(|(_, checked)| **checked)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+/// Where things were drawn at the last draw, to map mouse events back to them.
+#[derive(Default)]
+pub struct ScreenAreas {
+    pub list: Rect,
+    pub preview: Rect,
+    pub status: Rect,
+    /// Column ranges of the clickable answers to a confirmation in the status bar.
+    pub buttons: Vec<(Range<u16>, ConfirmAction)>,
+}
+
+/// The scroll position of the full-screen help overlay.
+#[derive(Default)]
+pub struct HelpScreen {
+    pub scroll: usize,
+    /// Number of lines of help, and of rows to show them in, at the last draw.
+    pub len: usize,
+    pub height: usize,
+}
+
+impl HelpScreen {
+    fn max_scroll(&self) -> usize {
+        self.len.saturating_sub(self.height)
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.max_scroll());
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn scroll_to_end(&mut self) {
+        self.scroll = self.max_scroll();
+    }
 }
 
 pub struct App {
+    pub settings: Settings,
     pub items: StatefulList,
     pub filter: String,
     pub filter_mode: bool,
+    /// Only list branches whose upstream is gone.
+    pub gone_only: bool,
     pub pending: String,
-    pub delete_confirmation: Option<String>,
+    pub delete_confirmation: Option<DeleteConfirmation>,
+    /// Branch the user wants to switch to while the working tree is dirty.
+    pub switch_confirmation: Option<BranchInfo>,
+    pub new_branch: Option<NewBranchPrompt>,
+    pub rename: Option<RenamePrompt>,
+    pub worktree_prompt: Option<WorktreePrompt>,
+    /// Branch the user chose that is checked out in another worktree.
+    pub worktree_confirmation: Option<BranchInfo>,
+    pub cleanup: Option<CleanupScreen>,
+    /// Fills in ahead/behind and merged state while the list is shown; None
+    /// once every branch has them.
+    pub details: Option<DetailsLoader>,
+    pub report: Option<BatchReport>,
+    pub help: Option<HelpScreen>,
+    pub show_preview: bool,
+    pub preview_mode: PreviewMode,
+    pub preview: Option<LogPreview>,
+    pub diff_preview: Option<DiffPreview>,
+    /// Number of rows the preview pane had at the last draw.
+    pub preview_height: usize,
+    pub areas: ScreenAreas,
+    /// When and on which row the last click landed, to spot double-clicks.
+    pub last_click: Option<(Instant, usize)>,
 }
 
 impl StatefulList {
     fn with_items(items: Vec<BranchInfo>) -> StatefulList {
         let filtered = Some(Box::new(items.clone()));
+        let item_rows = local_rows(&items);
         StatefulList {
             state: ListState::default(),
+            match_positions: vec![Vec::new(); items.len()],
             items,
             filtered,
+            marked: HashSet::new(),
+            mark_anchor: None,
+            filtered_rows: item_rows.clone(),
+            item_rows,
+        }
+    }
+
+    fn filtered_names(&self) -> Vec<String> {
+        self.filtered
+            .as_ref()
+            .map_or(Vec::new(), |f| f.iter().map(|b| b.branch_name.clone()).collect())
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        let index = self.state.selected()?;
+        self.filtered.as_ref()?.get(index).map(|b| b.branch_name.clone())
+    }
+
+    /// Marks the selected branch, or unmarks it if it was marked.
+    pub fn toggle_mark(&mut self) {
+        let Some(name) = self.selected_name() else {
+            return;
+        };
+        if !self.marked.remove(&name) {
+            self.marked.insert(name.clone());
+        }
+        self.mark_anchor = Some(name);
+    }
+
+    /// Marks every branch between the last one toggled and the selected one.
+    pub fn mark_range(&mut self) {
+        let Some(selected) = self.state.selected() else {
+            return;
+        };
+        let names = self.filtered_names();
+        let anchor = self
+            .mark_anchor
+            .as_ref()
+            .and_then(|anchor| names.iter().position(|name| name == anchor))
+            .unwrap_or(selected);
+        let (from, to) = (anchor.min(selected), anchor.max(selected));
+        self.marked.extend(names.into_iter().take(to + 1).skip(from));
+    }
+
+    /// Marks every listed branch, or unmarks them all if they all were marked.
+    pub fn toggle_mark_all(&mut self) {
+        let names = self.filtered_names();
+        if names.iter().all(|name| self.marked.contains(name)) {
+            for name in &names {
+                self.marked.remove(name);
+            }
+        } else {
+            self.marked.extend(names);
         }
     }
 
+    /// The marked branches, in list order.
+    pub fn marked_branches(&self) -> Vec<&BranchInfo> {
+        self.items
+            .iter()
+            .filter(|b| self.marked.contains(&b.branch_name))
+            .collect()
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.mark_anchor = None;
+    }
+
     pub fn unselect(&mut self) {
         self.state.select(None);
     }
@@ -116,13 +493,31 @@ pub struct NoSelectionError;
 
 impl App {
     #[must_use]
-    pub fn new(branches: Vec<BranchInfo>) -> App {
+    pub fn new(branches: Vec<BranchInfo>, settings: Settings) -> App {
         App {
+            show_preview: settings.show_preview,
+            settings,
             items: StatefulList::with_items(branches),
             filter: String::new(),
             filter_mode: false,
+            gone_only: false,
             pending: String::new(),
             delete_confirmation: None,
+            switch_confirmation: None,
+            new_branch: None,
+            rename: None,
+            worktree_prompt: None,
+            worktree_confirmation: None,
+            cleanup: None,
+            details: None,
+            report: None,
+            help: None,
+            preview_mode: PreviewMode::Log,
+            preview: None,
+            diff_preview: None,
+            preview_height: 0,
+            areas: ScreenAreas::default(),
+            last_click: None,
         }
     }
     pub fn select_first_item_if_none(&mut self) {
@@ -158,7 +553,7 @@ impl App {
 
     pub fn update_with_status(
         &mut self,
-        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        terminal: &mut Tui,
         pending_status: String,
     ) {
         self.filter.clear();
@@ -167,32 +562,86 @@ impl App {
 
     pub fn update_with_status_preserve_filter(
         &mut self,
-        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        terminal: &mut Tui,
         pending_status: String,
     ) {
         self.pending = pending_status;
         terminal.draw(|f| self.ui(f)).expect("error updating!");
     }
 
-    pub fn clear_pending_status(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) {
+    pub fn clear_pending_status(&mut self, terminal: &mut Tui) {
         self.pending.clear();
         terminal.draw(|f| self.ui(f)).expect("error updating!");
     }
 
+    pub fn toggle_gone_only(&mut self) {
+        self.gone_only = !self.gone_only;
+        self.update_filtered();
+    }
+
+    /// The answers to the confirmation being asked in the status bar, if any,
+    /// shown there as clickable buttons.
+    #[must_use]
+    pub fn confirm_choices(&self) -> Vec<(ConfirmAction, &'static str)> {
+        let checkout = self
+            .new_branch
+            .as_ref()
+            .is_some_and(|p| matches!(p.step, NewBranchStep::Checkout { .. }));
+        // typed confirmations have no buttons, as y and n are part of names
+        if let Some(confirmation) = self.delete_confirmation.as_ref() {
+            if confirmation.needs_typing() {
+                Vec::new()
+            } else {
+                vec![(ConfirmAction::Yes, "delete"), (ConfirmAction::No, "cancel")]
+            }
+        } else if let Some(cleanup) = self.cleanup.as_ref().filter(|c| c.confirming) {
+            if cleanup.next_to_type().is_some() {
+                Vec::new()
+            } else {
+                vec![(ConfirmAction::Yes, cleanup.operation.verb()), (ConfirmAction::No, "cancel")]
+            }
+        } else if self.switch_confirmation.is_some() {
+            vec![
+                (ConfirmAction::Stash, "stash them"),
+                (ConfirmAction::Carry, "carry them over"),
+                (ConfirmAction::Force, "discard them"),
+                (ConfirmAction::No, "cancel"),
+            ]
+        } else if self.worktree_confirmation.is_some() {
+            vec![
+                (ConfirmAction::Yes, "open a shell there"),
+                (ConfirmAction::Print, "print its path"),
+                (ConfirmAction::No, "cancel"),
+            ]
+        } else if self.rename.as_ref().is_some_and(|r| r.confirm_remote) {
+            vec![(ConfirmAction::Yes, "rename it too"), (ConfirmAction::No, "only locally")]
+        } else if checkout {
+            vec![(ConfirmAction::Yes, "check it out"), (ConfirmAction::No, "stay here")]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Filters the branches with the fuzzy matcher, ranking them by score and
+    /// keeping the recency order between equal scores.
     fn update_filtered(&mut self) {
-        let filtered: Vec<BranchInfo> = self
+        let mut matches: Vec<(i64, Vec<usize>, BranchInfo)> = self
             .items
             .items
-            .clone()
-            .into_iter()
-            .filter(|x| {
-                if self.filter.is_empty() {
-                    true
-                } else {
-                    x.branch_name.to_lowercase().contains(&self.filter.to_lowercase())
-                }
+            .iter()
+            .filter(|x| !self.gone_only || x.upstream_gone)
+            .filter_map(|x| {
+                fuzzy_match(&self.filter, &x.branch_name)
+                    .map(|(score, positions)| (score, positions, x.clone()))
             })
             .collect();
+        matches.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+        let (positions, filtered): (Vec<Vec<usize>>, Vec<BranchInfo>) = matches
+            .into_iter()
+            .map(|(_, positions, branch)| (positions, branch))
+            .unzip();
+        self.items.match_positions = positions;
+        self.items.filtered_rows = local_rows(&filtered);
         self.items.filtered = if filtered.is_empty() {
             self.items.state.select(None);
             None
@@ -202,8 +651,188 @@ impl App {
         };
     }
 
-    pub fn set_branches(&mut self, branches: Vec<BranchInfo>) {
+    /// Highlights the branch called `branch_name` if it is in the filtered list.
+    pub fn select_branch(&mut self, branch_name: &str) {
+        let index = self
+            .items
+            .filtered
+            .as_ref()
+            .and_then(|f| f.iter().position(|b| b.branch_name == branch_name));
+        if index.is_some() {
+            self.items.state.select(index);
+        }
+    }
+
+    /// Replaces the listed branches. Rows listed without their details keep
+    /// the ones shown before until `load_details` delivers fresh ones.
+    pub fn set_branches(&mut self, mut branches: Vec<BranchInfo>) {
+        let names: HashSet<&str> = branches.iter().map(|b| b.branch_name.as_str()).collect();
+        self.items.marked.retain(|name| names.contains(name.as_str()));
+        let previous: HashMap<&str, &BranchInfo> = self
+            .items
+            .items
+            .iter()
+            .filter(|b| b.remote.is_none())
+            .map(|b| (b.branch_name.as_str(), b))
+            .collect();
+        for branch in branches.iter_mut().filter(|b| b.remote.is_none() && b.ahead_behind.is_none()) {
+            if let Some(old) = previous.get(branch.branch_name.as_str()) {
+                branch.ahead_behind = old.ahead_behind;
+                branch.merged = old.merged;
+                if branch.remote_tracking.is_none() {
+                    branch.remote_tracking.clone_from(&old.remote_tracking);
+                }
+            }
+        }
+        self.items.item_rows = local_rows(&branches);
         self.items.items = branches;
+        self.preview = None;
+        self.diff_preview = None;
         self.update_filtered();
     }
+
+    /// Starts loading the details of the listed branches in the background,
+    /// cancelling any earlier load.
+    pub fn load_details(&mut self, repo: &Repo) {
+        self.details = Some(repo.load_details(&self.items.items));
+    }
+
+    /// Applies the details the background load has finished since the last
+    /// call. Returns whether anything changed, including the load finishing.
+    pub fn receive_details(&mut self) -> bool {
+        let Some(loader) = self.details.as_ref() else {
+            return false;
+        };
+        let mut received = Vec::new();
+        let mut finished = false;
+        loop {
+            match loader.receiver.try_recv() {
+                Ok(details) => received.push(details),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.details = None;
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        let list = &mut self.items;
+        for details in &received {
+            if let Some(&row) = list.item_rows.get(&details.branch_name) {
+                details.apply(&mut list.items[row]);
+            }
+            let row = list.filtered_rows.get(&details.branch_name);
+            if let (Some(&row), Some(filtered)) = (row, list.filtered.as_mut()) {
+                details.apply(&mut filtered[row]);
+            }
+        }
+        finished || !received.is_empty()
+    }
+
+    /// Makes sure the preview pane matches the highlighted branch and has
+    /// enough content loaded to fill it at its current scroll position.
+    pub fn refresh_preview(&mut self, repo: &Repo) {
+        if !self.show_preview {
+            return;
+        }
+        let Ok(info) = self.get_selected_branch_info() else {
+            self.preview = None;
+            self.diff_preview = None;
+            return;
+        };
+        match self.preview_mode {
+            PreviewMode::Log => self.refresh_log_preview(repo, &info),
+            PreviewMode::Diff => self.refresh_diff_preview(repo, &info),
+        }
+    }
+
+    fn refresh_log_preview(&mut self, repo: &Repo, info: &BranchInfo) {
+        let stale = self
+            .preview
+            .as_ref()
+            .filter(|p| p.branch_name == info.branch_name)
+            .is_none();
+        if stale {
+            self.preview = Some(LogPreview {
+                branch_name: info.branch_name.clone(),
+                commits: Vec::new(),
+                scroll: 0,
+                exhausted: false,
+                error: None,
+            });
+        }
+        let Some(preview) = self.preview.as_mut() else {
+            return;
+        };
+        while !preview.exhausted && preview.scroll + self.preview_height >= preview.commits.len() {
+            match repo.commit_log(info, preview.commits.len(), PREVIEW_PAGE_SIZE) {
+                Ok(commits) => {
+                    preview.exhausted = commits.len() < PREVIEW_PAGE_SIZE;
+                    preview.commits.extend(commits);
+                }
+                Err(error) => {
+                    preview.exhausted = true;
+                    preview.error = Some(error.message().to_string());
+                }
+            }
+        }
+    }
+
+    fn refresh_diff_preview(&mut self, repo: &Repo, info: &BranchInfo) {
+        let stale = self
+            .diff_preview
+            .as_ref()
+            .filter(|p| p.branch_name == info.branch_name)
+            .is_none();
+        if !stale {
+            return;
+        }
+        let (lines, error) = match repo.branch_diff(info) {
+            Ok(lines) => (lines, None),
+            Err(error) => (Vec::new(), Some(error.message().to_string())),
+        };
+        self.diff_preview = Some(DiffPreview {
+            branch_name: info.branch_name.clone(),
+            base: repo.base_branch().to_string(),
+            lines,
+            scroll: 0,
+            error,
+        });
+    }
+
+    pub fn toggle_preview_mode(&mut self) {
+        self.preview_mode = match self.preview_mode {
+            PreviewMode::Log => PreviewMode::Diff,
+            PreviewMode::Diff => PreviewMode::Log,
+        };
+    }
+
+    pub fn scroll_preview_down(&mut self) {
+        match self.preview_mode {
+            PreviewMode::Log => {
+                if let Some(preview) = self.preview.as_mut() {
+                    if preview.scroll + 1 < preview.commits.len() {
+                        preview.scroll += 1;
+                    }
+                }
+            }
+            PreviewMode::Diff => {
+                if let Some(preview) = self.diff_preview.as_mut() {
+                    if preview.scroll + 1 < preview.lines.len() {
+                        preview.scroll += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn scroll_preview_up(&mut self) {
+        let scroll = match self.preview_mode {
+            PreviewMode::Log => self.preview.as_mut().map(|p| &mut p.scroll),
+            PreviewMode::Diff => self.diff_preview.as_mut().map(|p| &mut p.scroll),
+        };
+        if let Some(scroll) = scroll {
+            *scroll = scroll.saturating_sub(1);
+        }
+    }
 }
@@ -1,46 +1,124 @@
 use clap::Parser;
+use crossterm::event::DisableMouseCapture;
 use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, LeaveAlternateScreen};
-use githist::git::branching::{Config, Repo};
+use githist::git::branching::{Command, Config, Repo};
+use githist::output::list::write_branches;
+use githist::settings::config::Settings;
+use githist::ui::run::app::Exit;
 use githist::ui::gui::{restore_terminal, setup_terminal};
 use githist::App;
+use std::env;
 use std::error::Error;
+use std::fmt::Display;
 use std::io;
+use std::io::Write;
 use std::panic;
+use std::process;
+
+/// Exit code in `--print` mode when the user quit without choosing a branch.
+const EXIT_CANCELLED: i32 = 1;
+/// Exit code in `--print` mode and for subcommands when something went wrong.
+const EXIT_ERROR: i32 = 2;
+
+/// Reports an error, exiting with `EXIT_ERROR` when run from a script: in
+/// `--print` mode, so it can be told apart from a cancelled selection, and
+/// for subcommands such as `list`.
+fn report_error(config: &Config, error: &impl Display) {
+    eprintln!("{error}");
+    if config.print || config.command.is_some() {
+        process::exit(EXIT_ERROR);
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
     let config = Config::parse();
 
-    let repo = match Repo::open(&config) {
+    let mut repo = match Repo::open(&config) {
         Ok(repo) => repo,
         Err(error) => {
-            eprintln!("{error:?}");
+            report_error(&config, &error.message());
+            return Ok(());
+        }
+    };
+
+    let settings = match Settings::load(&config, &repo) {
+        Ok(settings) => settings,
+        Err(error) => {
+            report_error(&config, &error);
             return Ok(());
         }
     };
+    repo.apply_settings(&settings);
 
-    match repo.get_branch_names() {
+    if let Some(Command::ClearCache) = config.command {
+        if let Err(error) = repo.clear_cache() {
+            report_error(&config, &error.message());
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::List { format }) = config.command {
+        match repo.get_branch_names() {
+            Ok(result) => match write_branches(&mut io::stdout().lock(), &result, format) {
+                // the reader went away, e.g. `githist list | head`
+                Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+                other => other?,
+            },
+            Err(error) => report_error(&config, &error.message()),
+        }
+        return Ok(());
+    }
+
+    match repo.get_branch_names_fast() {
         Ok(result) => {
-            let mut terminal = setup_terminal();
+            let mut terminal = setup_terminal(config.print, settings.mouse);
 
             // Install panic hook that restores the terminal before printing the panic.
+            let print = config.print;
             let original_hook = panic::take_hook();
             panic::set_hook(Box::new(move |panic_info| {
                 let _ = disable_raw_mode();
-                let _ = execute!(io::stdout(), LeaveAlternateScreen);
+                let _ = if print {
+                    execute!(io::stderr(), DisableMouseCapture, LeaveAlternateScreen)
+                } else {
+                    execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen)
+                };
                 original_hook(panic_info);
             }));
 
-            let mut app = App::new(result);
+            let mut app = App::new(result, settings);
+            app.load_details(&repo);
             app.select_first_item_if_none();
-            let res = app.run_app(&config, &repo, &mut terminal);
-            if let Err(err) = res {
-                eprintln!("{err:?}");
-            }
+            let res = app.run_app(&config, &mut repo, &mut terminal);
             restore_terminal(&mut terminal).expect("couldn't restore!");
+            if let Some(loader) = app.details.take() {
+                loader.finish();
+            }
+            match res {
+                Ok(Exit::Selected(branch_name)) => {
+                    let mut stdout = io::stdout().lock();
+                    writeln!(stdout, "{branch_name}")?;
+                    stdout.flush()?;
+                }
+                Ok(Exit::PrintPath(path)) => {
+                    let mut stdout = io::stdout().lock();
+                    writeln!(stdout, "{}", path.display())?;
+                    stdout.flush()?;
+                }
+                Ok(Exit::Shell(path)) => {
+                    let shell = env::var_os("SHELL").unwrap_or_else(|| "sh".into());
+                    eprintln!("starting a shell in {}", path.display());
+                    let status = process::Command::new(shell).current_dir(&path).status()?;
+                    process::exit(status.code().unwrap_or(EXIT_ERROR));
+                }
+                Ok(Exit::Quit) if config.print => process::exit(EXIT_CANCELLED),
+                Ok(_) => {}
+                Err(err) => report_error(&config, &err),
+            }
         }
         Err(error) => {
-            eprintln!("{error:?}");
+            report_error(&config, &error.message());
         }
     }
     Ok(())
@@ -0,0 +1,96 @@
+pub mod list {
+    use crate::git::branching::{BranchInfo, ListFormat};
+    use pad::PadStr;
+    use serde::Serialize;
+    use std::io;
+    use std::io::Write;
+    use std::path::Path;
+
+    /// The stable, serializable form of a `BranchInfo` printed by `githist list`.
+    #[derive(Serialize, Debug)]
+    pub struct BranchRecord<'a> {
+        pub name: &'a str,
+        /// Unix timestamp of the tip commit.
+        pub timestamp: i64,
+        pub relative_age: &'a str,
+        /// Unix timestamp of the last checkout of the branch, if in the reflog.
+        pub last_visited: Option<i64>,
+        pub head: bool,
+        pub ahead: Option<usize>,
+        pub behind: Option<usize>,
+        pub remote: Option<&'a str>,
+        pub merged: bool,
+        pub upstream_gone: bool,
+        /// Path of the other worktree the branch is checked out in.
+        pub worktree: Option<&'a Path>,
+    }
+
+    impl<'a> From<&'a BranchInfo> for BranchRecord<'a> {
+        fn from(info: &'a BranchInfo) -> Self {
+            BranchRecord {
+                name: &info.branch_name,
+                timestamp: info.last_commit_time,
+                relative_age: &info.time_ago,
+                last_visited: info.last_visited,
+                head: info.is_head,
+                ahead: info.ahead_behind.map(|(ahead, _)| ahead),
+                behind: info.ahead_behind.map(|(_, behind)| behind),
+                remote: info.remote.as_deref(),
+                merged: info.merged,
+                upstream_gone: info.upstream_gone,
+                worktree: info.worktree.as_deref(),
+            }
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Will return `io::Error` if writing to `out` failed.
+    pub fn write_branches(
+        out: &mut impl Write,
+        branches: &[BranchInfo],
+        format: ListFormat,
+    ) -> io::Result<()> {
+        // a detached or unborn HEAD gets a row in the TUI, but isn't a branch
+        let branches: Vec<&BranchInfo> = branches.iter().filter(|b| b.head_row.is_none()).collect();
+        let records: Vec<BranchRecord> = branches.iter().map(|b| BranchRecord::from(*b)).collect();
+        match format {
+            ListFormat::Plain => {
+                let width = records.iter().map(|r| r.name.len()).max().unwrap_or(0);
+                for (record, info) in records.iter().zip(branches) {
+                    let head_marker = if record.head { "* " } else { "  " };
+                    let tracking = info
+                        .remote_tracking
+                        .as_deref()
+                        .map_or(String::new(), |r| format!(" [{r}]"));
+                    writeln!(
+                        out,
+                        "{head_marker}{}   {}{tracking}",
+                        record.name.pad_to_width(width),
+                        record.relative_age
+                    )?;
+                }
+            }
+            ListFormat::Tsv => {
+                let count = |n: Option<usize>| n.map_or(String::new(), |n| n.to_string());
+                for record in &records {
+                    writeln!(
+                        out,
+                        "{}\t{}\t{}\t{}\t{}\t{}",
+                        record.name,
+                        record.timestamp,
+                        record.relative_age,
+                        record.head,
+                        count(record.ahead),
+                        count(record.behind)
+                    )?;
+                }
+            }
+            ListFormat::Json => {
+                serde_json::to_writer_pretty(&mut *out, &records)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+}
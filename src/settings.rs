@@ -0,0 +1,385 @@
+pub mod config {
+    use crate::git::branching::{Config, Repo, SortOrder};
+    use crate::keys::bindings::{Action, KeyBinding, KeyMaps};
+    use crate::theme::palette::{parse_color, parse_style, Theme, BUILTIN_THEMES};
+    use clap::ValueEnum;
+    use serde::Deserialize;
+    use std::error::Error;
+    use std::fmt;
+    use std::io;
+    use std::collections::{BTreeMap, HashMap};
+    use std::path::{Path, PathBuf};
+    use std::{env, fs};
+
+    /// Settings resolved from the built-in defaults, the config file, the
+    /// repository's `githist.*` git config and the command line, each
+    /// overriding the ones before it.
+    #[derive(Debug, Clone)]
+    pub struct Settings {
+        pub include_remotes: bool,
+        pub sort: SortOrder,
+        pub base: String,
+        pub pop_autostash: bool,
+        pub page_size: usize,
+        pub show_preview: bool,
+        /// Whether the TUI captures the mouse.
+        pub mouse: bool,
+        pub theme: Theme,
+        pub keys: KeyMaps,
+        theme_name: String,
+    }
+
+    impl Default for Settings {
+        fn default() -> Self {
+            Settings {
+                include_remotes: false,
+                sort: SortOrder::Checkout,
+                base: "main".to_string(),
+                pop_autostash: false,
+                page_size: 10,
+                show_preview: true,
+                mouse: true,
+                theme: Theme::default(),
+                keys: KeyMaps::default(),
+                theme_name: Theme::default_name().to_string(),
+            }
+        }
+    }
+
+    /// The contents of `config.toml`.
+    #[derive(Deserialize, Default)]
+    #[serde(deny_unknown_fields, rename_all = "kebab-case")]
+    struct FileSettings {
+        all: Option<bool>,
+        sort: Option<SortOrder>,
+        base: Option<String>,
+        pop_autostash: Option<bool>,
+        page_size: Option<usize>,
+        preview: Option<bool>,
+        mouse: Option<bool>,
+        theme: Option<String>,
+        #[serde(default)]
+        colors: FileColors,
+        #[serde(default)]
+        themes: BTreeMap<String, FileTheme>,
+        #[serde(default)]
+        keys: BTreeMap<Action, FileKeys>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(deny_unknown_fields, rename_all = "kebab-case")]
+    struct FileColors {
+        row_fg: Option<String>,
+        row_bg: Option<String>,
+        highlight_bg: Option<String>,
+        head: Option<String>,
+        tracking: Option<String>,
+        remote: Option<String>,
+        merged: Option<String>,
+        matched: Option<String>,
+    }
+
+    /// A user-defined theme from `[themes.<name>]`, starting from a built-in
+    /// one and overriding some of its styles.
+    #[derive(Deserialize)]
+    struct FileTheme {
+        inherits: Option<String>,
+        #[serde(flatten)]
+        styles: BTreeMap<String, String>,
+    }
+
+    /// The parts of the config file that can only be applied once every
+    /// layer has had its say on which theme to use.
+    #[derive(Default)]
+    struct Theming {
+        path: PathBuf,
+        themes: BTreeMap<String, FileTheme>,
+        colors: FileColors,
+    }
+
+    /// A single key or a list of keys for one action in `[keys]`.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FileKeys {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    impl FileKeys {
+        fn into_vec(self) -> Vec<String> {
+            match self {
+                FileKeys::One(key) => vec![key],
+                FileKeys::Many(keys) => keys,
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub enum SettingsError {
+        /// The config file exists but couldn't be read.
+        Read { path: PathBuf, error: io::Error },
+        /// The config file isn't valid TOML or has unknown or mistyped keys.
+        Parse { path: PathBuf, error: toml::de::Error },
+        /// A setting has a value githist doesn't understand.
+        Invalid { setting: String, message: String },
+        /// The repository's git config couldn't be read.
+        Git(git2::Error),
+    }
+
+    impl fmt::Display for SettingsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                SettingsError::Read { path, error } => {
+                    write!(f, "couldn't read {}: {error}", path.display())
+                }
+                SettingsError::Parse { path, error } => {
+                    write!(f, "invalid config file {}: {error}", path.display())
+                }
+                SettingsError::Invalid { setting, message } => write!(f, "{setting}: {message}"),
+                SettingsError::Git(error) => write!(f, "couldn't read git config: {error}"),
+            }
+        }
+    }
+
+    impl Error for SettingsError {}
+
+    /// `$XDG_CONFIG_HOME/githist/config.toml`, falling back to `~/.config`.
+    pub fn config_file_path() -> Option<PathBuf> {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("githist").join("config.toml"))
+    }
+
+    impl Theming {
+        /// Builds the theme called `name`, a built-in one or one from
+        /// `[themes]`, with the file's `[colors]` tweaks on top.
+        fn resolve(&self, name: &str) -> Result<Theme, SettingsError> {
+            let setting = |name: &str| format!("{}: {name}", self.path.display());
+            let invalid = |setting: String, message: String| SettingsError::Invalid { setting, message };
+
+            let mut theme = match (Theme::builtin(name), self.themes.get(name)) {
+                (_, Some(user_theme)) => {
+                    let inherits = user_theme.inherits.as_deref().unwrap_or("terminal");
+                    let mut theme = Theme::builtin(inherits).ok_or_else(|| {
+                        invalid(
+                            setting(&format!("themes.{name}.inherits")),
+                            format!(
+                                "unknown theme '{inherits}', expected one of {}",
+                                BUILTIN_THEMES.join(", ")
+                            ),
+                        )
+                    })?;
+                    for (key, value) in &user_theme.styles {
+                        let setting = setting(&format!("themes.{name}.{key}"));
+                        let style =
+                            parse_style(value).map_err(|message| invalid(setting.clone(), message))?;
+                        let slot = theme
+                            .style_mut(key)
+                            .ok_or_else(|| invalid(setting, "unknown style".to_string()))?;
+                        *slot = style;
+                    }
+                    theme
+                }
+                (Some(theme), None) => theme,
+                (None, None) => {
+                    let known: Vec<&str> = BUILTIN_THEMES
+                        .into_iter()
+                        .chain(self.themes.keys().map(String::as_str))
+                        .collect();
+                    return Err(invalid(
+                        "theme".to_string(),
+                        format!("unknown theme '{name}', expected one of {}", known.join(", ")),
+                    ));
+                }
+            };
+
+            // the older [colors] table sets the foreground or background of
+            // a theme style
+            let colors = &self.colors;
+            let overrides = [
+                ("row-fg", &colors.row_fg, "row", false),
+                ("row-bg", &colors.row_bg, "row", true),
+                ("highlight-bg", &colors.highlight_bg, "highlight", true),
+                ("head", &colors.head, "head", false),
+                ("tracking", &colors.tracking, "tracking", false),
+                ("remote", &colors.remote, "remote", false),
+                ("merged", &colors.merged, "merged", false),
+                ("matched", &colors.matched, "matched", false),
+            ];
+            for (key, value, style, background) in overrides {
+                let (Some(value), Some(style)) = (value, theme.style_mut(style)) else {
+                    continue;
+                };
+                let setting = setting(&format!("colors.{key}"));
+                let color = parse_color(value).map_err(|message| invalid(setting, message))?;
+                *style = if background { style.bg(color) } else { style.fg(color) };
+            }
+            Ok(theme)
+        }
+    }
+
+    fn check_page_size(setting: &str, page_size: usize) -> Result<usize, SettingsError> {
+        if page_size == 0 {
+            Err(SettingsError::Invalid {
+                setting: setting.to_string(),
+                message: "must be at least 1".to_string(),
+            })
+        } else {
+            Ok(page_size)
+        }
+    }
+
+    /// Reads `key` from git config, treating a missing key as unset.
+    fn git_value<T>(
+        key: &str,
+        value: Result<T, git2::Error>,
+    ) -> Result<Option<T>, SettingsError> {
+        match value {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if error.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(error) => Err(SettingsError::Invalid {
+                setting: format!("git config {key}"),
+                message: error.message().to_string(),
+            }),
+        }
+    }
+
+    impl Settings {
+        /// # Errors
+        ///
+        /// Will return `SettingsError` if the config file can't be read or
+        /// parsed, or if any setting has an invalid value.
+        pub fn load(config: &Config, repo: &Repo) -> Result<Settings, SettingsError> {
+            let mut settings = Settings::default();
+            let mut theming = Theming::default();
+            if let Some(path) = config_file_path().filter(|path| path.exists()) {
+                theming = settings.apply_file(&path)?;
+            }
+            let git_config = repo.git_config().map_err(SettingsError::Git)?;
+            settings.apply_git_config(&git_config)?;
+            settings.apply_cli(config);
+            settings.theme = theming.resolve(&settings.theme_name)?;
+            Ok(settings)
+        }
+
+        fn apply_file(&mut self, path: &Path) -> Result<Theming, SettingsError> {
+            let contents = fs::read_to_string(path).map_err(|error| SettingsError::Read {
+                path: path.to_path_buf(),
+                error,
+            })?;
+            let file: FileSettings =
+                toml::from_str(&contents).map_err(|error| SettingsError::Parse {
+                    path: path.to_path_buf(),
+                    error,
+                })?;
+
+            if let Some(all) = file.all {
+                self.include_remotes = all;
+            }
+            if let Some(sort) = file.sort {
+                self.sort = sort;
+            }
+            if let Some(base) = file.base {
+                self.base = base;
+            }
+            if let Some(pop_autostash) = file.pop_autostash {
+                self.pop_autostash = pop_autostash;
+            }
+            let setting = |name: &str| format!("{}: {name}", path.display());
+            if let Some(page_size) = file.page_size {
+                self.page_size = check_page_size(&setting("page-size"), page_size)?;
+            }
+            if let Some(preview) = file.preview {
+                self.show_preview = preview;
+            }
+            if let Some(mouse) = file.mouse {
+                self.mouse = mouse;
+            }
+            if let Some(theme) = file.theme {
+                self.theme_name = theme;
+            }
+
+            let mut claimed: HashMap<KeyBinding, Action> = HashMap::new();
+            for (action, keys) in file.keys {
+                let setting = setting(&format!("keys.{}", action.name()));
+                let mut bindings = Vec::new();
+                for key in keys.into_vec() {
+                    let binding = KeyBinding::parse(&key).ok_or_else(|| SettingsError::Invalid {
+                        setting: setting.clone(),
+                        message: format!("unknown key '{key}'"),
+                    })?;
+                    if let Some(other) = claimed.insert(binding, action).filter(|a| *a != action) {
+                        return Err(SettingsError::Invalid {
+                            setting,
+                            message: format!("'{key}' is also bound to {}", other.name()),
+                        });
+                    }
+                    bindings.push(binding);
+                }
+                self.keys.normal.rebind(action, &bindings);
+            }
+            Ok(Theming {
+                path: path.to_path_buf(),
+                themes: file.themes,
+                colors: file.colors,
+            })
+        }
+
+        fn apply_git_config(&mut self, git_config: &git2::Config) -> Result<(), SettingsError> {
+            if let Some(all) = git_value("githist.all", git_config.get_bool("githist.all"))? {
+                self.include_remotes = all;
+            }
+            if let Some(sort) = git_value("githist.sort", git_config.get_string("githist.sort"))? {
+                self.sort = SortOrder::from_str(&sort, true).map_err(|_| {
+                    SettingsError::Invalid {
+                        setting: "git config githist.sort".to_string(),
+                        message: format!("expected checkout or commit, found '{sort}'"),
+                    }
+                })?;
+            }
+            if let Some(base) = git_value("githist.base", git_config.get_string("githist.base"))? {
+                self.base = base;
+            }
+            let pop_autostash = git_config.get_bool("githist.popAutostash");
+            if let Some(pop_autostash) = git_value("githist.popAutostash", pop_autostash)? {
+                self.pop_autostash = pop_autostash;
+            }
+            let page_size = git_config.get_i64("githist.pageSize");
+            if let Some(page_size) = git_value("githist.pageSize", page_size)? {
+                let page_size = usize::try_from(page_size).unwrap_or(0);
+                self.page_size = check_page_size("git config githist.pageSize", page_size)?;
+            }
+            let preview = git_config.get_bool("githist.preview");
+            if let Some(preview) = git_value("githist.preview", preview)? {
+                self.show_preview = preview;
+            }
+            if let Some(mouse) = git_value("githist.mouse", git_config.get_bool("githist.mouse"))? {
+                self.mouse = mouse;
+            }
+            if let Some(theme) = git_value("githist.theme", git_config.get_string("githist.theme"))? {
+                self.theme_name = theme;
+            }
+            Ok(())
+        }
+
+        fn apply_cli(&mut self, config: &Config) {
+            if let Some(include_remotes) = config.include_remotes() {
+                self.include_remotes = include_remotes;
+            }
+            if let Some(pop_autostash) = config.pop_autostash() {
+                self.pop_autostash = pop_autostash;
+            }
+            if let Some(sort) = config.sort {
+                self.sort = sort;
+            }
+            if let Some(base) = config.base.as_ref() {
+                self.base.clone_from(base);
+            }
+            if let Some(theme) = config.theme.as_ref() {
+                self.theme_name.clone_from(theme);
+            }
+        }
+    }
+}
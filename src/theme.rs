@@ -0,0 +1,248 @@
+pub mod palette {
+    use ratatui::style::{Color, Modifier, Style};
+    use std::env;
+    use std::str::FromStr;
+
+    /// Every style the TUI draws with.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Theme {
+        /// A branch row, and the rows of the cleanup screen.
+        pub row: Style,
+        /// Patched over the selected row.
+        pub highlight: Style,
+        /// The `*` marking the checked out branch.
+        pub head: Style,
+        /// The `[ahead 1, behind 2]` upstream summary.
+        pub tracking: Style,
+        /// The `(origin)` marker on remote-tracking branches.
+        pub remote: Style,
+        /// The path of the other worktree a branch is checked out in.
+        pub worktree: Style,
+        pub merged: Style,
+        /// Characters matched by the filter.
+        pub matched: Style,
+        /// The branch name being edited by a rename.
+        pub renaming: Style,
+        pub commit_id: Style,
+        pub commit_author: Style,
+        pub commit_time: Style,
+        pub diff_stat: Style,
+        pub diff_file: Style,
+        pub diff_hunk: Style,
+        pub diff_added: Style,
+        pub diff_removed: Style,
+        /// Section titles in the help overlay and batch reports.
+        pub heading: Style,
+        /// The mark next to branches marked for a batch action.
+        pub marked: Style,
+        /// Branches a batch action succeeded or failed on.
+        pub success: Style,
+        pub failure: Style,
+    }
+
+    /// The names of the built-in themes.
+    pub const BUILTIN_THEMES: [&str; 4] = ["terminal", "light", "dark", "monochrome"];
+
+    impl Default for Theme {
+        fn default() -> Self {
+            Theme::terminal()
+        }
+    }
+
+    impl Theme {
+        /// The terminal's own foreground and background, with ANSI colors for
+        /// accents and reverse video for the selection.
+        pub fn terminal() -> Theme {
+            Theme {
+                row: Style::default(),
+                highlight: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+                head: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                tracking: Style::default().fg(Color::Cyan),
+                remote: Style::default().fg(Color::Magenta),
+                worktree: Style::default().fg(Color::Blue),
+                merged: Style::default().fg(Color::Green),
+                matched: Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                renaming: Style::default().add_modifier(Modifier::UNDERLINED),
+                commit_id: Style::default().fg(Color::Yellow),
+                commit_author: Style::default().fg(Color::Cyan),
+                commit_time: Style::default().add_modifier(Modifier::DIM),
+                diff_stat: Style::default().add_modifier(Modifier::BOLD),
+                diff_file: Style::default().add_modifier(Modifier::BOLD),
+                diff_hunk: Style::default().fg(Color::Cyan),
+                diff_added: Style::default().fg(Color::Green),
+                diff_removed: Style::default().fg(Color::Red),
+                heading: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                marked: Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                success: Style::default().fg(Color::Green),
+                failure: Style::default().fg(Color::Red),
+            }
+        }
+
+        /// Black on white, the original githist look.
+        pub fn light() -> Theme {
+            Theme {
+                row: Style::default().fg(Color::Black).bg(Color::White),
+                highlight: Style::default()
+                    .bg(Color::LightGreen)
+                    .add_modifier(Modifier::BOLD),
+                commit_id: Style::default().fg(Color::Blue),
+                commit_time: Style::default().fg(Color::DarkGray),
+                diff_file: Style::default().fg(Color::Black).add_modifier(Modifier::BOLD),
+                ..Theme::terminal()
+            }
+        }
+
+        pub fn dark() -> Theme {
+            Theme {
+                row: Style::default().fg(Color::Gray).bg(Color::Black),
+                highlight: Style::default()
+                    .fg(Color::White)
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD),
+                head: Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+                tracking: Style::default().fg(Color::LightCyan),
+                remote: Style::default().fg(Color::LightMagenta),
+                worktree: Style::default().fg(Color::LightBlue),
+                merged: Style::default().fg(Color::LightGreen),
+                matched: Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                commit_id: Style::default().fg(Color::LightYellow),
+                commit_author: Style::default().fg(Color::LightCyan),
+                commit_time: Style::default().fg(Color::DarkGray),
+                diff_file: Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+                diff_hunk: Style::default().fg(Color::LightCyan),
+                diff_added: Style::default().fg(Color::LightGreen),
+                diff_removed: Style::default().fg(Color::LightRed),
+                heading: Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+                marked: Style::default().fg(Color::LightBlue).add_modifier(Modifier::BOLD),
+                success: Style::default().fg(Color::LightGreen),
+                failure: Style::default().fg(Color::LightRed),
+                ..Theme::terminal()
+            }
+        }
+
+        /// No colors at all: emphasis comes from bold, underline and
+        /// reverse video only.
+        pub fn monochrome() -> Theme {
+            Theme {
+                row: Style::default(),
+                highlight: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+                head: Style::default().add_modifier(Modifier::BOLD),
+                tracking: Style::default(),
+                remote: Style::default().add_modifier(Modifier::ITALIC),
+                worktree: Style::default().add_modifier(Modifier::ITALIC),
+                merged: Style::default().add_modifier(Modifier::DIM),
+                matched: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                renaming: Style::default().add_modifier(Modifier::UNDERLINED),
+                commit_id: Style::default().add_modifier(Modifier::BOLD),
+                commit_author: Style::default(),
+                commit_time: Style::default().add_modifier(Modifier::DIM),
+                diff_stat: Style::default().add_modifier(Modifier::BOLD),
+                diff_file: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                diff_hunk: Style::default().add_modifier(Modifier::DIM),
+                diff_added: Style::default().add_modifier(Modifier::BOLD),
+                diff_removed: Style::default().add_modifier(Modifier::DIM),
+                heading: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                marked: Style::default().add_modifier(Modifier::BOLD),
+                success: Style::default(),
+                failure: Style::default().add_modifier(Modifier::BOLD),
+            }
+        }
+
+        pub fn builtin(name: &str) -> Option<Theme> {
+            match name {
+                "terminal" => Some(Theme::terminal()),
+                "light" => Some(Theme::light()),
+                "dark" => Some(Theme::dark()),
+                "monochrome" => Some(Theme::monochrome()),
+                _ => None,
+            }
+        }
+
+        /// The theme used when none is configured: monochrome when
+        /// `NO_COLOR` is set to a non-empty value, terminal otherwise.
+        pub fn default_name() -> &'static str {
+            if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+                "monochrome"
+            } else {
+                "terminal"
+            }
+        }
+
+        /// The style slot for a config key like `diff-added`.
+        pub fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+            let style = match name {
+                "row" => &mut self.row,
+                "highlight" => &mut self.highlight,
+                "head" => &mut self.head,
+                "tracking" => &mut self.tracking,
+                "remote" => &mut self.remote,
+                "worktree" => &mut self.worktree,
+                "merged" => &mut self.merged,
+                "matched" => &mut self.matched,
+                "renaming" => &mut self.renaming,
+                "commit-id" => &mut self.commit_id,
+                "commit-author" => &mut self.commit_author,
+                "commit-time" => &mut self.commit_time,
+                "diff-stat" => &mut self.diff_stat,
+                "diff-file" => &mut self.diff_file,
+                "diff-hunk" => &mut self.diff_hunk,
+                "diff-added" => &mut self.diff_added,
+                "diff-removed" => &mut self.diff_removed,
+                "heading" => &mut self.heading,
+                "marked" => &mut self.marked,
+                "success" => &mut self.success,
+                "failure" => &mut self.failure,
+                _ => return None,
+            };
+            Some(style)
+        }
+    }
+
+    /// Parses a style like `bold yellow`, `black on white` or `reverse`.
+    /// Words are modifiers (`bold`, `dim`, `italic`, `underline`, `reverse`),
+    /// a foreground color, or `on` followed by a background color.
+    ///
+    /// # Errors
+    ///
+    /// Will return a description of the first word that isn't understood.
+    pub fn parse_style(value: &str) -> Result<Style, String> {
+        let mut style = Style::default();
+        let mut words = value.split_whitespace();
+        while let Some(word) = words.next() {
+            let modifier = match word.to_lowercase().as_str() {
+                "bold" => Some(Modifier::BOLD),
+                "dim" => Some(Modifier::DIM),
+                "italic" => Some(Modifier::ITALIC),
+                "underline" | "underlined" => Some(Modifier::UNDERLINED),
+                "reverse" | "reversed" => Some(Modifier::REVERSED),
+                "none" | "plain" => continue,
+                _ => None,
+            };
+            if let Some(modifier) = modifier {
+                style = style.add_modifier(modifier);
+            } else if word.eq_ignore_ascii_case("on") {
+                let bg = words.next().ok_or("expected a color after 'on'")?;
+                style = style.bg(parse_color(bg)?);
+            } else {
+                style = style.fg(parse_color(word)?);
+            }
+        }
+        Ok(style)
+    }
+
+    /// Parses a color name like `lightgreen`, an index like `208` or `#rrggbb`.
+    ///
+    /// # Errors
+    ///
+    /// Will return a description of the problem if `value` isn't a color.
+    pub fn parse_color(value: &str) -> Result<Color, String> {
+        match value.to_lowercase().as_str() {
+            "default" => Ok(Color::Reset),
+            _ => Color::from_str(value).map_err(|_| format!("unknown color '{value}'")),
+        }
+    }
+}
@@ -1,45 +1,83 @@
 pub mod run;
 
 pub mod gui {
-    use crate::App;
+    use crate::git::branching::{DiffLineKind, HeadRow};
+    use crate::keys::bindings::{Action, HelpAction, PromptAction, StartPointAction};
+    use crate::{commits, App, BatchReport, NewBranchStep, PreviewMode};
+    use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
     use crossterm::execute;
     use crossterm::terminal::{
         disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
     };
     use pad::PadStr;
     use ratatui::backend::CrosstermBackend;
-    use ratatui::layout::{Constraint, Direction, Layout};
-    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::layout::{Constraint, Direction, Layout, Rect};
+    use ratatui::style::Style;
     use ratatui::text::{Line, Span};
-    use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Wrap};
+    use ratatui::widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap};
     use ratatui::{Frame, Terminal};
     use std::io;
-    use std::io::Stdout;
+    use std::io::Write;
 
-    pub fn setup_terminal() -> Terminal<CrosstermBackend<Stdout>> {
+    /// The terminal the TUI draws on: stdout, or stderr when stdout is
+    /// reserved for printing the chosen branch.
+    pub type Tui = Terminal<CrosstermBackend<Box<dyn Write>>>;
+
+    pub fn setup_terminal(use_stderr: bool, mouse: bool) -> Tui {
         enable_raw_mode().expect("failed to enter raw mode!");
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen).expect("failed to setup terminal!");
-        let backend = CrosstermBackend::new(stdout);
+        let mut out: Box<dyn Write> = if use_stderr {
+            Box::new(io::stderr())
+        } else {
+            Box::new(io::stdout())
+        };
+        execute!(out, EnterAlternateScreen).expect("failed to setup terminal!");
+        if mouse {
+            execute!(out, EnableMouseCapture).expect("failed to capture the mouse!");
+        }
+        let backend = CrosstermBackend::new(out);
         Terminal::new(backend).expect("failed to instance terminal")
     }
 
-    pub fn restore_terminal(
-        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    ) -> Result<(), io::Error> {
+    pub fn restore_terminal(terminal: &mut Tui) -> Result<(), io::Error> {
         disable_raw_mode()?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
+        execute!(terminal.backend_mut(), DisableMouseCapture, LeaveAlternateScreen)?;
         Ok(())
     }
 
+    /// Splits `text` into spans, emphasizing the chars at `positions`.
+    fn highlight_matches(text: &str, positions: &[usize], matched_style: Style) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        let mut run = String::new();
+        let mut run_matched = false;
+        for (index, c) in text.chars().enumerate() {
+            let matched = positions.contains(&index);
+            if matched != run_matched && !run.is_empty() {
+                let style = if run_matched { matched_style } else { Style::default() };
+                spans.push(Span::styled(std::mem::take(&mut run), style));
+            }
+            run_matched = matched;
+            run.push(c);
+        }
+        if !run.is_empty() {
+            let style = if run_matched { matched_style } else { Style::default() };
+            spans.push(Span::styled(run, style));
+        }
+        spans
+    }
+
     impl App {
         pub(crate) fn ui(&mut self, f: &mut Frame) {
+            // the status area grows to fit a batch report, up to half the screen
+            let status_height = self.report.as_ref().map_or(1, |report| {
+                let lines = u16::try_from(report.results.len() + 1).unwrap_or(u16::MAX);
+                lines.min(f.size().height / 2).max(1)
+            });
             let chunks = Layout::default()
                 .constraints(
                     [
-                        Constraint::Percentage(96),
-                        Constraint::Percentage(2),
-                        Constraint::Percentage(2),
+                        Constraint::Min(0),
+                        Constraint::Length(1),
+                        Constraint::Length(status_height),
                     ]
                     .as_ref(),
                 )
@@ -55,44 +93,101 @@ pub mod gui {
                 .max()
                 .unwrap_or(0);
 
+            let theme = &self.settings.theme;
+            let selected = self.items.state.selected();
             let items: Vec<ListItem> = self
                 .items
                 .filtered
                 .clone()
                 .unwrap_or_default()
                 .into_iter()
-                .map(|branch_info| {
+                .enumerate()
+                .map(|(index, branch_info)| {
                     let head_marker = if branch_info.is_head { "* " } else { "  " };
-                    let branch_and_padding =
-                        branch_info.branch_name.pad_to_width(largest_string_len);
+                    let marked = self.items.marked.contains(&branch_info.branch_name);
+                    let renaming = self
+                        .rename
+                        .as_ref()
+                        .filter(|_| selected == Some(index))
+                        .map(|r| format!("{}_", r.input));
+                    let branch_and_padding = renaming
+                        .as_deref()
+                        .unwrap_or(&branch_info.branch_name)
+                        .pad_to_width(largest_string_len);
                     let remote_info = branch_info
                         .remote_tracking
                         .as_deref()
                         .map_or(String::new(), |r| format!(" [{r}]"));
 
                     let mut spans = vec![
+                        if marked {
+                            Span::styled("+", theme.marked)
+                        } else {
+                            Span::raw(" ")
+                        },
                         Span::styled(
                             head_marker,
                             if branch_info.is_head {
-                                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                                theme.head
                             } else {
                                 Style::default()
                             },
                         ),
-                        Span::raw(format!(
-                            "{}   changed: {}",
-                            branch_and_padding, branch_info.time_ago
-                        )),
                     ];
+                    if renaming.is_some() {
+                        spans.push(Span::styled(
+                            branch_and_padding,
+                            theme.renaming,
+                        ));
+                    } else {
+                        let positions = self
+                            .items
+                            .match_positions
+                            .get(index)
+                            .map_or(&[][..], Vec::as_slice);
+                        spans.extend(highlight_matches(
+                            &branch_and_padding,
+                            positions,
+                            theme.matched,
+                        ));
+                    }
+                    match &branch_info.head_row {
+                        Some(HeadRow::Unborn) => spans.push(Span::raw("   no commits yet")),
+                        Some(HeadRow::Detached { description, .. }) => {
+                            spans.push(Span::raw(format!("   changed: {}", branch_info.time_ago)));
+                            spans.push(Span::styled(format!(" {description}"), theme.tracking));
+                        }
+                        None => {
+                            spans.push(Span::raw(format!("   changed: {}", branch_info.time_ago)));
+                        }
+                    }
+                    if let Some(visited_ago) = branch_info.visited_ago.as_deref() {
+                        spans.push(Span::raw(format!("   visited: {visited_ago}")));
+                    }
                     if !remote_info.is_empty() {
                         spans.push(Span::styled(
                             remote_info,
-                            Style::default().fg(Color::Cyan),
+                            theme.tracking,
+                        ));
+                    }
+                    if branch_info.merged {
+                        spans.push(Span::styled(" merged", theme.merged));
+                    }
+                    if let Some(remote) = branch_info.remote.as_deref() {
+                        spans.push(Span::styled(
+                            format!(" ({remote})"),
+                            theme.remote,
+                        ));
+                    }
+                    if let Some(worktree) = branch_info.worktree.as_deref() {
+                        spans.push(Span::styled(
+                            format!(" (worktree: {})", worktree.display()),
+                            theme.worktree,
                         ));
                     }
 
                     ListItem::new(Line::from(spans))
-                        .style(Style::default().fg(Color::Black).bg(Color::White))
+                        .style(theme.row)
                 })
                 .collect();
 
@@ -102,34 +197,138 @@ pub mod gui {
                 format!("{}/{} branches", self.filtered_len(), self.total_len())
             };
 
-            let title = format!("choose recent branch  ({count_info})");
+            let mut title = if self.gone_only {
+                format!("branches with a gone upstream  ({count_info})")
+            } else {
+                format!("choose recent branch  ({count_info})")
+            };
+            if self.details.is_some() {
+                title.push_str("  checking upstreams...");
+            }
             let items = List::new(items)
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
                         .title(title),
                 )
-                .highlight_style(
-                    Style::default()
-                        .bg(Color::LightGreen)
-                        .add_modifier(Modifier::BOLD),
-                )
+                .highlight_style(theme.highlight)
                 .highlight_symbol(">> ");
 
-            let instructions_text =
-                "q/Esc: quit | j/k/↓/↑: navigate | ↩: switch branch | Shift+D: delete | /: filter | g/G: first/last | PgUp/PgDn: page";
+            // the row only fits a few keys; the help overlay lists the rest
+            let instructions_text = self.settings.keys.normal.summary(&[
+                Action::Switch,
+                Action::Filter,
+                Action::NewBranch,
+                Action::Delete,
+                Action::Quit,
+                Action::Help,
+            ]);
             let instructions_para = Paragraph::new(instructions_text)
                 .block(Block::default().borders(Borders::NONE))
                 .wrap(Wrap { trim: true });
 
-            // list of branches
-            f.render_stateful_widget(items, chunks[0], &mut self.items.state);
+            // list of branches, with the log preview alongside when enabled
+            self.areas.list = chunks[0];
+            self.areas.preview = Rect::default();
+            self.areas.status = chunks[2];
+            if self.cleanup.is_some() {
+                self.render_cleanup(f, chunks[0]);
+            } else if self.show_preview {
+                let panes = Layout::default()
+                    .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+                    .direction(Direction::Horizontal)
+                    .split(chunks[0]);
+                self.areas.list = panes[0];
+                self.areas.preview = panes[1];
+                f.render_stateful_widget(items, panes[0], &mut self.items.state);
+                self.render_preview(f, panes[1]);
+            } else {
+                f.render_stateful_widget(items, chunks[0], &mut self.items.state);
+            }
 
             // instructions
             f.render_widget(instructions_para, chunks[1]);
 
             // status bar: show filter, pending status, or filter mode indicator
-            let status_text = if !self.pending.is_empty() {
+            let status_text = if let Some(prompt) = self.new_branch.as_ref() {
+                let error = if self.pending.is_empty() {
+                    String::new()
+                } else {
+                    format!("  ({})", self.pending)
+                };
+                match &prompt.step {
+                    NewBranchStep::Name => format!("new branch name: {}_{error}", prompt.input),
+                    NewBranchStep::StartPoint { name } => {
+                        let keys = &self.settings.keys;
+                        let choice = |action, label: &str| {
+                            format!("{}: {label}", keys.start_point.describe_keys(action))
+                        };
+                        let mut choices = vec![choice(StartPointAction::Head, "HEAD")];
+                        if let Some(branch) = prompt.highlighted.as_ref() {
+                            choices.push(choice(StartPointAction::Highlighted, &branch.branch_name));
+                        }
+                        choices.push(choice(StartPointAction::Revspec, "revspec"));
+                        let cancel = keys.prompt.describe_keys(PromptAction::Cancel);
+                        choices.push(format!("{cancel}: cancel"));
+                        format!("start {name} from? {}", choices.join(" | "))
+                    }
+                    NewBranchStep::Revspec { name } => {
+                        format!("start {name} from revspec: {}_{error}", prompt.input)
+                    }
+                    NewBranchStep::Checkout { name } => {
+                        format!("created branch {name}. check it out?")
+                    }
+                }
+            } else if let Some(cleanup) = self.cleanup.as_ref() {
+                let count = cleanup.checked.iter().filter(|c| **c).count();
+                if let Some((name, unmerged)) =
+                    cleanup.next_to_type().filter(|_| cleanup.confirming)
+                {
+                    format!(
+                        "{name} has {} on no other branch. type its name to delete it: {}_",
+                        commits(unmerged),
+                        cleanup.input
+                    )
+                } else if cleanup.confirming {
+                    format!("{} {count} branches?", cleanup.operation.verb())
+                } else if !self.pending.is_empty() {
+                    format!("status: {}", self.pending)
+                } else {
+                    self.settings.keys.cleanup.help_line()
+                }
+            } else if let Some(confirmation) =
+                self.delete_confirmation.as_ref().filter(|c| c.needs_typing())
+            {
+                format!(
+                    "{} has {} on no other branch. type its name to delete it: {}_",
+                    confirmation.branch_name,
+                    commits(confirmation.unmerged_commits),
+                    confirmation.input
+                )
+            } else if let Some(prompt) = self.worktree_prompt.as_ref() {
+                if self.pending.is_empty() {
+                    format!("new worktree for {} at: {}_", prompt.branch_name, prompt.input)
+                } else {
+                    format!(
+                        "new worktree for {} at: {}_  ({})",
+                        prompt.branch_name, prompt.input, self.pending
+                    )
+                }
+            } else if let Some(rename) = self.rename.as_ref() {
+                if rename.confirm_remote {
+                    format!("also rename the upstream of {} on the remote?", rename.branch_name)
+                } else if self.pending.is_empty() {
+                    let keys = &self.settings.keys.prompt;
+                    format!(
+                        "renaming {}: {} to confirm, {} to cancel",
+                        rename.branch_name,
+                        keys.describe_keys(PromptAction::Accept),
+                        keys.describe_keys(PromptAction::Cancel)
+                    )
+                } else {
+                    format!("renaming {}: {}", rename.branch_name, self.pending)
+                }
+            } else if !self.pending.is_empty() {
                 format!("status: {}", self.pending)
             } else if self.filter_mode {
                 format!("filter: {}_", self.filter)
@@ -139,12 +338,228 @@ pub mod gui {
                 String::new()
             };
 
-            if !status_text.is_empty() {
-                let status_para = Paragraph::new(status_text)
-                    .block(Block::default().borders(Borders::NONE))
-                    .wrap(Wrap { trim: true });
-                f.render_widget(status_para, chunks[2]);
+            // answers to a confirmation follow the question as buttons
+            let mut spans = vec![Span::raw(status_text)];
+            let mut x = chunks[2].x + spans[0].width() as u16;
+            self.areas.buttons.clear();
+            for (action, label) in self.confirm_choices() {
+                let keys = &self.settings.keys.confirm;
+                let key = keys.keys_for(action).next().map_or(String::new(), |k| k.to_string());
+                let button = Span::styled(format!("[{key}: {label}]"), self.settings.theme.highlight);
+                let width = button.width() as u16;
+                spans.push(Span::raw(" "));
+                self.areas.buttons.push((x + 1..x + 1 + width, action));
+                spans.push(button);
+                x += 1 + width;
             }
+
+            let status_para = match self.report.as_ref() {
+                Some(report) => self.render_report(report),
+                None => Paragraph::new(Line::from(spans)),
+            };
+            let status_para = status_para
+                .block(Block::default().borders(Borders::NONE))
+                .wrap(Wrap { trim: true });
+            f.render_widget(status_para, chunks[2]);
+
+            if self.help.is_some() {
+                self.render_help(f, f.size());
+            }
+        }
+
+        /// The result of a batch operation for every branch it ran on.
+        fn render_report(&self, report: &BatchReport) -> Paragraph<'static> {
+            let theme = &self.settings.theme;
+            let width = report
+                .results
+                .iter()
+                .map(|(name, _)| name.chars().count())
+                .max()
+                .unwrap_or(0);
+            let mut lines = vec![Line::styled(report.title.clone(), theme.heading)];
+            for (name, result) in &report.results {
+                let (mark, message, style) = match result {
+                    Ok(message) => ("ok", message, theme.success),
+                    Err(error) => ("failed", error, theme.failure),
+                };
+                lines.push(Line::from(vec![
+                    Span::raw(format!("  {}  ", name.pad_to_width(width))),
+                    Span::styled(format!("{mark}: {message}"), style),
+                ]));
+            }
+            Paragraph::new(lines)
+        }
+
+        /// Draws the help overlay over the whole screen, listing the keys of
+        /// every mode.
+        fn render_help(&mut self, f: &mut Frame, area: Rect) {
+            let theme = &self.settings.theme;
+            let Some(help) = self.help.as_mut() else {
+                return;
+            };
+            let sections = self.settings.keys.help_sections();
+            let width = sections
+                .iter()
+                .flat_map(|(_, entries)| entries.iter().map(|(keys, _)| keys.chars().count()))
+                .max()
+                .unwrap_or(0);
+
+            let mut lines = Vec::new();
+            for (title, entries) in sections {
+                if !lines.is_empty() {
+                    lines.push(Line::default());
+                }
+                lines.push(Line::styled(title, theme.heading));
+                for (keys, description) in entries {
+                    lines.push(Line::from(format!("  {}  {description}", keys.pad_to_width(width))));
+                }
+            }
+
+            let close = self
+                .settings
+                .keys
+                .help
+                .describe_keys(HelpAction::Close);
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title(format!("help ({close} to close)"));
+            help.height = block.inner(area).height as usize;
+            help.len = lines.len();
+            help.scroll = help.scroll.min(help.len.saturating_sub(help.height));
+
+            let para = Paragraph::new(lines)
+                .style(theme.row)
+                .block(block)
+                .scroll((u16::try_from(help.scroll).unwrap_or(u16::MAX), 0));
+            f.render_widget(Clear, area);
+            f.render_widget(para, area);
+        }
+
+        fn render_cleanup(&mut self, f: &mut Frame, area: Rect) {
+            let theme = &self.settings.theme;
+            let Some(cleanup) = self.cleanup.as_mut() else {
+                return;
+            };
+            let items: Vec<ListItem> = cleanup
+                .branches
+                .iter()
+                .zip(&cleanup.checked)
+                .zip(&cleanup.unmerged_commits)
+                .map(|((name, checked), unmerged)| {
+                    let mark = if *checked { "[x] " } else { "[ ] " };
+                    let mut spans = vec![Span::raw(format!("{mark}{name}"))];
+                    if *unmerged > 0 {
+                        let warning = format!("  {} on no other branch", commits(*unmerged));
+                        spans.push(Span::styled(warning, theme.failure));
+                    }
+                    ListItem::new(Line::from(spans)).style(theme.row)
+                })
+                .collect();
+            let list = List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(cleanup.title.clone()),
+                )
+                .highlight_style(theme.highlight)
+                .highlight_symbol(">> ");
+            f.render_stateful_widget(list, area, &mut cleanup.state);
+        }
+
+        fn render_preview(&mut self, f: &mut Frame, area: Rect) {
+            match self.preview_mode {
+                PreviewMode::Log => self.render_log_preview(f, area),
+                PreviewMode::Diff => self.render_diff_preview(f, area),
+            }
+        }
+
+        fn render_log_preview(&mut self, f: &mut Frame, area: Rect) {
+            let theme = &self.settings.theme;
+            let title = self
+                .preview
+                .as_ref()
+                .map_or("log".to_string(), |p| format!("log: {}", p.branch_name));
+            let block = Block::default().borders(Borders::ALL).title(title);
+            self.preview_height = block.inner(area).height as usize;
+
+            let lines: Vec<Line> = match self.preview.as_ref() {
+                Some(preview) => {
+                    let mut lines: Vec<Line> = preview
+                        .commits
+                        .iter()
+                        .skip(preview.scroll)
+                        .map(|commit| {
+                            Line::from(vec![
+                                Span::styled(
+                                    commit.short_id.clone(),
+                                    theme.commit_id,
+                                ),
+                                Span::raw(" "),
+                                Span::styled(
+                                    commit.author.clone(),
+                                    theme.commit_author,
+                                ),
+                                Span::raw(" "),
+                                Span::styled(
+                                    format!("({})", commit.time_ago),
+                                    theme.commit_time,
+                                ),
+                                Span::raw(format!(" {}", commit.summary)),
+                            ])
+                        })
+                        .collect();
+                    if let Some(error) = preview.error.as_deref() {
+                        lines.push(Line::from(format!("couldn't read log: {error}")));
+                    }
+                    lines
+                }
+                None => Vec::new(),
+            };
+
+            let para = Paragraph::new(lines).style(theme.row).block(block);
+            f.render_widget(para, area);
+        }
+
+        fn render_diff_preview(&mut self, f: &mut Frame, area: Rect) {
+            let theme = &self.settings.theme;
+            let title = self.diff_preview.as_ref().map_or("diff".to_string(), |p| {
+                format!("diff: {} (since {})", p.branch_name, p.base)
+            });
+            let block = Block::default().borders(Borders::ALL).title(title);
+            self.preview_height = block.inner(area).height as usize;
+
+            let lines: Vec<Line> = match self.diff_preview.as_ref() {
+                Some(preview) => {
+                    let mut lines: Vec<Line> = preview
+                        .lines
+                        .iter()
+                        .skip(preview.scroll)
+                        .take(self.preview_height)
+                        .map(|line| {
+                            let style = match line.kind {
+                                DiffLineKind::Stat => theme.diff_stat,
+                                DiffLineKind::FileHeader => theme.diff_file,
+                                DiffLineKind::HunkHeader => theme.diff_hunk,
+                                DiffLineKind::Added => theme.diff_added,
+                                DiffLineKind::Removed => theme.diff_removed,
+                                DiffLineKind::Context => Style::default(),
+                            };
+                            Line::styled(line.text.clone(), style)
+                        })
+                        .collect();
+                    if preview.lines.is_empty() && preview.error.is_none() {
+                        lines.push(Line::from(format!("no changes since {}", preview.base)));
+                    }
+                    if let Some(error) = preview.error.as_deref() {
+                        lines.push(Line::from(format!("couldn't compute diff: {error}")));
+                    }
+                    lines
+                }
+                None => Vec::new(),
+            };
+
+            let para = Paragraph::new(lines).style(theme.row).block(block);
+            f.render_widget(para, area);
         }
     }
 }
@@ -1,205 +1,1214 @@
 pub mod app {
-    use crate::git::branching::{Config, Repo};
-    use crate::App;
+    use crate::git::branching::{
+        BranchInfo, Config, HeadRow, Repo, SwitchStrategy, UndoStep, BARE_CHECKOUT,
+    };
+    use crate::keys::bindings::{
+        Action, CleanupAction, ConfirmAction, FilterAction, HelpAction, KeyMap, PromptAction,
+        StartPointAction,
+    };
+    use crate::{
+        App, BatchOperation, BatchReport, CleanupScreen, DeleteConfirmation, HelpScreen,
+        NewBranchPrompt, NewBranchStep, RenamePrompt, WorktreePrompt,
+    };
     use crossterm::event;
-    use crossterm::event::{Event, KeyCode, KeyModifiers};
-    use ratatui::backend::CrosstermBackend;
-    use ratatui::Terminal;
+    use crossterm::event::{
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    };
+    use ratatui::layout::Rect;
+    use crate::ui::gui::Tui;
     use std::io;
-    use std::io::Stdout;
+    use std::path::{Path, PathBuf};
     use std::time::{Duration, Instant};
 
-    const PAGE_SIZE: usize = 10;
+    /// Two clicks on the same row within this long make a double-click.
+    const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+    /// How often the event loop looks for branch details while they load.
+    const DETAILS_POLL: Duration = Duration::from_millis(10);
+
+    /// The index of the list row drawn at `row`, for a bordered list in
+    /// `area` scrolled down by `offset` rows.
+    fn list_row_at(area: Rect, offset: usize, column: u16, row: u16) -> Option<usize> {
+        let inside = column > area.x
+            && column + 1 < area.right()
+            && row > area.y
+            && row + 1 < area.bottom();
+        inside.then(|| offset + usize::from(row - area.y - 1))
+    }
+
+    /// A key pressed in a text prompt.
+    enum PromptKey {
+        Action(PromptAction),
+        /// A character to type, possibly with Shift.
+        Char(char),
+        Other,
+    }
+
+    fn prompt_key(keys: &KeyMap<PromptAction>, key: KeyEvent) -> PromptKey {
+        if let Some(action) = keys.action_for(key) {
+            return PromptKey::Action(action);
+        }
+        match key.code {
+            KeyCode::Char(c) if key.modifiers.is_empty() || key.modifiers == KeyModifiers::SHIFT => {
+                PromptKey::Char(c)
+            }
+            _ => PromptKey::Other,
+        }
+    }
+
+    /// How the TUI was left.
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Exit {
+        /// The user quit without choosing a branch.
+        Quit,
+        /// The chosen branch was checked out.
+        Switched,
+        /// A branch was chosen in `--print` mode.
+        Selected(String),
+        /// A branch checked out in another worktree was chosen, to print the
+        /// worktree's path.
+        PrintPath(PathBuf),
+        /// A branch checked out in another worktree was chosen, to open a
+        /// shell in it.
+        Shell(PathBuf),
+    }
 
     impl App {
-        /// # Errors
-        ///
-        /// Will return `Err` if `self.ui()` failed.
-        ///
-        pub fn run_app(
+        /// Checks out the branch described by `info`, creating a local tracking
+        /// branch first for remote-tracking ones. Returns whether the switch
+        /// succeeded; failures are reported in the status bar.
+        fn switch_branch(
+            &mut self,
+            repo: &mut Repo,
+            terminal: &mut Tui,
+            info: &BranchInfo,
+            strategy: SwitchStrategy,
+        ) -> bool {
+            let status = format!("switching to branch: {}", info.branch_name);
+            self.update_with_status(terminal, status);
+            let local_name = match info.remote.as_deref() {
+                Some(remote) => repo.local_branch_for_remote(remote, &info.branch_name),
+                None => Ok(info.branch_name.clone()),
+            };
+            let local_name = match local_name {
+                Ok(name) => name,
+                Err(error) => {
+                    let status = format!("couldn't create local branch: {error}");
+                    self.update_with_status_preserve_filter(terminal, status);
+                    return false;
+                }
+            };
+            if let Err(error) = repo.change_branch(&local_name, strategy) {
+                let status = format!("couldn't change branch: {error}");
+                self.update_with_status_preserve_filter(terminal, status);
+                return false;
+            }
+            if self.settings.pop_autostash {
+                if let Err(error) = repo.pop_autostash(&local_name) {
+                    let status =
+                        format!("switched to {local_name} but couldn't pop autostash: {error}");
+                    self.update_with_status_preserve_filter(terminal, status);
+                    return false;
+                }
+            }
+            true
+        }
+
+        /// Acts on the user choosing a branch: returns it in `--print` mode,
+        /// otherwise checks it out, asking first when it is in another worktree
+        /// or the working tree is dirty. Returns how to leave the TUI, if at all.
+        fn choose_branch(
             &mut self,
+            info: BranchInfo,
             config: &Config,
+            repo: &mut Repo,
+            terminal: &mut Tui,
+        ) -> Option<Exit> {
+            if config.print {
+                let name = if let Some(HeadRow::Detached { .. }) = info.head_row {
+                    // the row's label isn't a ref, so give scripts the commit
+                    match repo.tip_id(&info) {
+                        Ok(id) => id.to_string(),
+                        Err(error) => {
+                            let status = format!("can't read HEAD: {}", error.message());
+                            self.update_with_status_preserve_filter(terminal, status);
+                            return None;
+                        }
+                    }
+                } else {
+                    match info.remote.as_deref() {
+                        Some(remote) => info
+                            .branch_name
+                            .strip_prefix(&format!("{remote}/"))
+                            .unwrap_or(&info.branch_name)
+                            .to_string(),
+                        None => info.branch_name,
+                    }
+                };
+                return Some(Exit::Selected(name));
+            }
+            if let Some(worktree) = info.worktree.as_deref() {
+                let status = format!("{} is checked out in {}.", info.branch_name, worktree.display());
+                self.worktree_confirmation = Some(info.clone());
+                self.update_with_status_preserve_filter(terminal, status);
+            } else if repo.is_bare() {
+                let keys = self.settings.keys.normal.describe_keys(Action::AddWorktree);
+                let mut status = BARE_CHECKOUT.to_string();
+                if !keys.is_empty() && info.remote.is_none() {
+                    status.push_str(&format!(" ({keys} adds a worktree for it)"));
+                }
+                self.update_with_status_preserve_filter(terminal, status);
+            } else if info.is_head {
+                let status = if info.is_branch() {
+                    format!("already on branch '{}'", info.branch_name)
+                } else {
+                    format!("already on {}", info.branch_name)
+                };
+                self.update_with_status_preserve_filter(terminal, status);
+            } else if repo.is_dirty() {
+                let status = format!("local changes! switch to {}?", info.branch_name);
+                self.switch_confirmation = Some(info);
+                self.update_with_status_preserve_filter(terminal, status);
+            } else if self.switch_branch(repo, terminal, &info, SwitchStrategy::Carry) {
+                return Some(Exit::Switched);
+            }
+            None
+        }
+
+        /// Reloads the branch list, keeping the selection on `branch_name` if it
+        /// is still listed and on the same row otherwise.
+        fn reload_branches(&mut self, repo: &Repo, branch_name: &str) -> Result<(), git2::Error> {
+            let selected_index = self.items.state.selected();
+            let branches = repo.get_branch_names_fast()?;
+            self.set_branches(branches);
+            self.load_details(repo);
+            if let Some(idx) = selected_index {
+                let new_len = self.filtered_len();
+                if new_len > 0 {
+                    self.items.state.select(Some(idx.min(new_len - 1)));
+                }
+            }
+            self.select_branch(branch_name);
+            Ok(())
+        }
+
+        /// Handles a key press on the "clean up merged" screen.
+        fn handle_cleanup_key(
+            &mut self,
+            key: KeyEvent,
             repo: &Repo,
-            terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-        ) -> io::Result<()> {
-            let mut last_tick = Instant::now();
-            loop {
-                terminal.draw(|f| self.ui(f))?;
+            terminal: &mut Tui,
+        ) {
+            let Some(cleanup) = self.cleanup.as_mut() else {
+                return;
+            };
+            self.pending.clear();
+            let keys = &self.settings.keys;
+            if let Some((name, _)) = cleanup.next_to_type().filter(|_| cleanup.confirming) {
+                let typed = match prompt_key(&keys.prompt, key) {
+                    PromptKey::Action(PromptAction::Cancel) => {
+                        cleanup.cancel_confirming();
+                        return;
+                    }
+                    PromptKey::Action(PromptAction::DeleteChar) => {
+                        cleanup.input.pop();
+                        return;
+                    }
+                    PromptKey::Char(c) => {
+                        cleanup.input.push(c);
+                        return;
+                    }
+                    PromptKey::Action(PromptAction::Accept) => cleanup.input == name,
+                    PromptKey::Other => return,
+                };
+                // start over on a typo rather than make it be found and fixed
+                if !typed {
+                    cleanup.input.clear();
+                    return;
+                }
+                if let Some(i) = cleanup.branches.iter().position(|b| *b == cleanup.input) {
+                    cleanup.typed[i] = true;
+                }
+                cleanup.input.clear();
+                if cleanup.next_to_type().is_some() {
+                    return;
+                }
+            } else if cleanup.confirming {
+                match keys.confirm.action_for(key) {
+                    Some(ConfirmAction::Yes) => {}
+                    Some(ConfirmAction::No) => {
+                        cleanup.cancel_confirming();
+                        return;
+                    }
+                    _ => return,
+                }
+            } else {
+                match keys.cleanup.action_for(key) {
+                    Some(CleanupAction::Back) => self.cleanup = None,
+                    Some(CleanupAction::Down) => cleanup.next(),
+                    Some(CleanupAction::Up) => cleanup.previous(),
+                    Some(CleanupAction::Toggle) => cleanup.toggle_selected(),
+                    Some(CleanupAction::ToggleAll) => cleanup.toggle_all(),
+                    Some(CleanupAction::Run) => {
+                        if cleanup.checked.contains(&true) {
+                            cleanup.confirming = true;
+                        } else {
+                            self.pending = "nothing checked, nothing to do!".to_string();
+                        }
+                    }
+                    None => {}
+                }
+                return;
+            }
 
-                let timeout = config
-                    .tick_rate()
-                    .checked_sub(last_tick.elapsed())
-                    .unwrap_or_else(|| Duration::from_secs(0));
-                if event::poll(timeout)? {
-                    if let Event::Key(key) = event::read()? {
-                        // Delete confirmation mode
-                        if let Some(branch_name) = self.delete_confirmation.clone() {
-                            match key.code {
-                                KeyCode::Char('Y') | KeyCode::Char('y') => {
-                                    self.delete_confirmation = None;
-                                    let selected_index = self.items.state.selected();
-                                    match repo.delete_branch(&branch_name) {
-                                        Ok(_) => match repo.get_branch_names() {
-                                            Ok(branches) => {
-                                                self.set_branches(branches);
-                                                if let Some(idx) = selected_index {
-                                                    let new_len = self.filtered_len();
-                                                    if new_len > 0 {
-                                                        let new_idx = idx.min(new_len - 1);
-                                                        self.items.state.select(Some(new_idx));
-                                                    }
-                                                }
-                                                let status =
-                                                    format!("deleted branch: {}", branch_name);
-                                                self.update_with_status_preserve_filter(
-                                                    terminal, status,
-                                                );
-                                            }
-                                            Err(error) => {
-                                                let status = format!(
-                                                    "deleted branch but failed to refresh list: {error}"
-                                                );
-                                                self.update_with_status_preserve_filter(
-                                                    terminal, status,
-                                                );
-                                            }
-                                        },
-                                        Err(error) => {
-                                            let status = format!(
-                                                "couldn't delete branch {branch_name}: {error}"
-                                            );
-                                            self.update_with_status_preserve_filter(
-                                                terminal, status,
-                                            );
-                                        }
-                                    }
-                                }
-                                KeyCode::Char('N')
-                                | KeyCode::Char('n')
-                                | KeyCode::Esc
-                                | KeyCode::Backspace => {
-                                    self.delete_confirmation = None;
-                                    self.clear_pending_status(terminal);
-                                }
-                                _ => {}
+            let Some(cleanup) = self.cleanup.take() else {
+                return;
+            };
+            let operation = cleanup.operation;
+            let step = UndoStep::new();
+            let mut results = Vec::new();
+            for branch_name in cleanup.checked_branches() {
+                let status = format!("{}: {branch_name}...", operation.verb());
+                self.update_with_status_preserve_filter(terminal, status);
+                let result = match operation {
+                    BatchOperation::Delete => {
+                        repo.delete_branch(&branch_name, step).map(|()| "deleted".to_string())
+                    }
+                    BatchOperation::Push => repo.push_branch(&branch_name),
+                    BatchOperation::Fetch => repo.fetch_upstream(&branch_name),
+                    BatchOperation::Rebase => repo.rebase_onto_base(&branch_name),
+                };
+                results.push((branch_name, result.map_err(|error| error.message().to_string())));
+            }
+
+            let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+            let mut title = format!(
+                "{}: {} succeeded, {failed} failed",
+                operation.verb(),
+                results.len() - failed
+            );
+            if operation == BatchOperation::Delete && failed < results.len() {
+                title.push_str(&self.undo_hint());
+            }
+            let selected = self.get_selected_branch_name().unwrap_or_default();
+            if let Err(error) = self.reload_branches(repo, &selected) {
+                title.push_str(&format!(", but failed to refresh list: {error}"));
+            }
+            self.items.clear_marks();
+            self.pending.clear();
+            self.report = Some(BatchReport { title, results });
+        }
+
+        /// Tells how to undo a deletion, if undo is bound to a key.
+        fn undo_hint(&self) -> String {
+            let keys = self.settings.keys.normal.describe_keys(Action::Undo);
+            if keys.is_empty() {
+                String::new()
+            } else {
+                format!(" ({keys} to undo)")
+            }
+        }
+
+        /// Restores the branches removed by the most recent deletion, here or
+        /// in an earlier session, and selects the first of them.
+        fn undo_deletion(&mut self, repo: &Repo, terminal: &mut Tui) {
+            let results = match repo.undo_deletion() {
+                Ok(results) => results,
+                Err(error) => {
+                    let status = format!("can't undo: {}", error.message());
+                    self.update_with_status_preserve_filter(terminal, status);
+                    return;
+                }
+            };
+            let restored = results
+                .iter()
+                .find(|(_, result)| result.is_ok())
+                .map(|(name, _)| name.clone());
+            let refreshed = self
+                .reload_branches(repo, restored.as_deref().unwrap_or_default())
+                .err()
+                .map(|error| format!(", but failed to refresh list: {error}"))
+                .unwrap_or_default();
+            let status = match results.as_slice() {
+                [(name, Ok(()))] => format!("restored branch: {name}{refreshed}"),
+                [(name, Err(error))] => {
+                    format!("couldn't restore {name}: {}{refreshed}", error.message())
+                }
+                _ => {
+                    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+                    let title = format!(
+                        "undo delete: {} restored, {failed} failed{refreshed}",
+                        results.len() - failed
+                    );
+                    let results = results
+                        .into_iter()
+                        .map(|(name, result)| {
+                            let result = result
+                                .map(|()| "restored".to_string())
+                                .map_err(|error| error.message().to_string());
+                            (name, result)
+                        })
+                        .collect();
+                    self.report = Some(BatchReport { title, results });
+                    String::new()
+                }
+            };
+            self.update_with_status_preserve_filter(terminal, status);
+        }
+
+        /// Opens the checklist for running `operation` on the marked local
+        /// branches, or on the selected one if none are marked, asking for
+        /// confirmation straight away.
+        fn start_batch(&mut self, operation: BatchOperation, repo: &Repo, terminal: &mut Tui) {
+            let targets: Vec<BranchInfo> = if self.items.marked.is_empty() {
+                self.get_selected_branch_info().into_iter().collect()
+            } else {
+                self.items.marked_branches().into_iter().cloned().collect()
+            };
+            let branches: Vec<String> = targets
+                .into_iter()
+                .filter(|b| b.remote.is_none() && b.head_row.is_none())
+                // checked out branches can't be deleted, nor rebased from another worktree
+                .filter(|b| match operation {
+                    BatchOperation::Delete => !(b.is_head || b.worktree.is_some()),
+                    BatchOperation::Rebase => b.worktree.is_none(),
+                    BatchOperation::Push | BatchOperation::Fetch => true,
+                })
+                .map(|b| b.branch_name)
+                .collect();
+            if branches.is_empty() {
+                let status = format!("no local branches to {}", operation.verb());
+                self.update_with_status_preserve_filter(terminal, status);
+                return;
+            }
+            let title = format!("{} {} branches", operation.verb(), branches.len());
+            self.open_checklist(CleanupScreen::new(title, branches, operation), true, repo, terminal);
+        }
+
+        /// Commits deleting a local branch would leave unreachable, or none
+        /// when its changes are in the base branch already, say by a squash.
+        fn unmerged_commits(&self, repo: &Repo, branch_name: &str) -> Result<usize, git2::Error> {
+            let merged = self
+                .items
+                .items
+                .iter()
+                .any(|b| b.remote.is_none() && b.branch_name == branch_name && b.merged);
+            if merged {
+                Ok(0)
+            } else {
+                repo.unmerged_commits(branch_name)
+            }
+        }
+
+        /// Shows a checklist of branches, counting the unmerged commits of
+        /// each first when they are to be deleted.
+        fn open_checklist(
+            &mut self,
+            mut cleanup: CleanupScreen,
+            confirming: bool,
+            repo: &Repo,
+            terminal: &mut Tui,
+        ) {
+            if cleanup.operation == BatchOperation::Delete {
+                let counts: Result<Vec<usize>, _> = cleanup
+                    .branches
+                    .iter()
+                    .map(|name| self.unmerged_commits(repo, name))
+                    .collect();
+                match counts {
+                    Ok(counts) => cleanup.unmerged_commits = counts,
+                    Err(error) => {
+                        let status = format!("can't count unmerged commits: {}", error.message());
+                        self.update_with_status_preserve_filter(terminal, status);
+                        return;
+                    }
+                }
+            }
+            self.pending.clear();
+            cleanup.confirming = confirming;
+            self.cleanup = Some(cleanup);
+        }
+
+        /// Handles a key press while the inline rename editor is open.
+        fn handle_rename_key(
+            &mut self,
+            key: KeyEvent,
+            repo: &Repo,
+            terminal: &mut Tui,
+        ) {
+            let Some(mut rename) = self.rename.take() else {
+                return;
+            };
+            self.pending.clear();
+            let keys = &self.settings.keys;
+            let rename_upstream = match prompt_key(&keys.prompt, key) {
+                PromptKey::Action(PromptAction::Cancel) => {
+                    self.clear_pending_status(terminal);
+                    return;
+                }
+                _ if rename.confirm_remote => match keys.confirm.action_for(key) {
+                    Some(ConfirmAction::Yes) => Some(true),
+                    Some(ConfirmAction::No) => Some(false),
+                    _ => None,
+                },
+                PromptKey::Action(PromptAction::DeleteChar) => {
+                    rename.input.pop();
+                    None
+                }
+                PromptKey::Char(c) => {
+                    rename.input.push(c);
+                    None
+                }
+                PromptKey::Action(PromptAction::Accept) => {
+                    let new_name = rename.input.trim().to_string();
+                    rename.input.clone_from(&new_name);
+                    if new_name == rename.branch_name {
+                        self.clear_pending_status(terminal);
+                        return;
+                    }
+                    match Repo::validate_branch_name(&new_name) {
+                        Ok(()) if repo.has_upstream(&rename.branch_name) => {
+                            rename.confirm_remote = true;
+                            None
+                        }
+                        Ok(()) => Some(false),
+                        Err(error) => {
+                            self.pending = error.message().to_string();
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            let Some(rename_upstream) = rename_upstream else {
+                self.rename = Some(rename);
+                return;
+            };
+            let result = repo.rename_branch(&rename.branch_name, &rename.input, rename_upstream);
+            let status = match result {
+                Ok(()) => match self.reload_branches(repo, &rename.input) {
+                    Ok(()) => format!("renamed branch {} to {}", rename.branch_name, rename.input),
+                    Err(error) => format!("renamed branch but failed to refresh list: {error}"),
+                },
+                Err(error) => format!("couldn't rename branch {}: {error}", rename.branch_name),
+            };
+            self.update_with_status_preserve_filter(terminal, status);
+        }
+
+        /// Handles a key press while editing the path of a new worktree.
+        fn handle_worktree_key(&mut self, key: KeyEvent, repo: &Repo, terminal: &mut Tui) {
+            let Some(mut prompt) = self.worktree_prompt.take() else {
+                return;
+            };
+            self.pending.clear();
+            match prompt_key(&self.settings.keys.prompt, key) {
+                PromptKey::Action(PromptAction::Cancel) => {
+                    self.clear_pending_status(terminal);
+                    return;
+                }
+                PromptKey::Action(PromptAction::DeleteChar) => {
+                    prompt.input.pop();
+                }
+                PromptKey::Char(c) => {
+                    prompt.input.push(c);
+                }
+                PromptKey::Action(PromptAction::Accept) if !prompt.input.trim().is_empty() => {
+                    let path = Path::new(prompt.input.trim());
+                    match repo.add_worktree(&prompt.branch_name, path) {
+                        Ok(path) => {
+                            let mut status = format!("created worktree at {}", path.display());
+                            if let Err(error) = self.reload_branches(repo, &prompt.branch_name) {
+                                status.push_str(&format!(", but failed to refresh list: {error}"));
                             }
-                            continue;
+                            // offer to go there straight away
+                            self.worktree_confirmation = self
+                                .get_selected_branch_info()
+                                .ok()
+                                .filter(|info| info.worktree.is_some());
+                            self.update_with_status_preserve_filter(terminal, status);
+                            return;
                         }
+                        Err(error) => self.pending = error.message().to_string(),
+                    }
+                }
+                _ => {}
+            }
+            self.worktree_prompt = Some(prompt);
+        }
 
-                        // Filter mode: typing goes to the filter
-                        if self.filter_mode {
-                            match key.code {
-                                KeyCode::Esc | KeyCode::Enter => {
-                                    self.filter_mode = false;
-                                }
-                                KeyCode::Backspace => {
-                                    if self.filter.pop().is_none() {
-                                        self.filter_mode = false;
-                                    }
-                                    self.update_filtered();
+        /// Handles a key press while the "new branch" prompts are open. Returns
+        /// whether the new branch was checked out.
+        fn handle_new_branch_key(
+            &mut self,
+            key: KeyEvent,
+            config: &Config,
+            repo: &mut Repo,
+            terminal: &mut Tui,
+        ) -> Option<Exit> {
+            let mut prompt = self.new_branch.take()?;
+            self.pending.clear();
+            let keys = &self.settings.keys;
+            let typed = prompt_key(&keys.prompt, key);
+            let create = match (&prompt.step, typed) {
+                (_, PromptKey::Action(PromptAction::Cancel)) => {
+                    self.clear_pending_status(terminal);
+                    return None;
+                }
+                (NewBranchStep::StartPoint { name }, _) => {
+                    match keys.start_point.action_for(key) {
+                        Some(StartPointAction::Head) => Some((name.clone(), "HEAD".to_string())),
+                        Some(StartPointAction::Highlighted) => prompt
+                            .highlighted
+                            .as_ref()
+                            .map(|info| (name.clone(), Repo::branch_refname(info))),
+                        Some(StartPointAction::Revspec) => {
+                            prompt.step = NewBranchStep::Revspec { name: name.clone() };
+                            None
+                        }
+                        None => None,
+                    }
+                }
+                (NewBranchStep::Checkout { name }, _) => match keys.confirm.action_for(key) {
+                    Some(ConfirmAction::Yes) => {
+                        let info = self
+                            .items
+                            .items
+                            .iter()
+                            .find(|b| b.remote.is_none() && &b.branch_name == name)
+                            .cloned();
+                        if let Some(info) = info {
+                            return self.choose_branch(info, config, repo, terminal);
+                        }
+                        let status = format!("created branch {name} but it isn't listed");
+                        self.update_with_status_preserve_filter(terminal, status);
+                        return None;
+                    }
+                    Some(ConfirmAction::No) => {
+                        let status = format!("created branch {name}");
+                        self.update_with_status_preserve_filter(terminal, status);
+                        return None;
+                    }
+                    _ => None,
+                },
+                (_, PromptKey::Action(PromptAction::DeleteChar)) => {
+                    prompt.input.pop();
+                    None
+                }
+                (_, PromptKey::Char(c)) => {
+                    prompt.input.push(c);
+                    None
+                }
+                (NewBranchStep::Name, PromptKey::Action(PromptAction::Accept)) => {
+                    let name = prompt.input.trim().to_string();
+                    match Repo::validate_branch_name(&name) {
+                        Ok(()) => {
+                            prompt.input.clear();
+                            prompt.step = NewBranchStep::StartPoint { name };
+                        }
+                        Err(error) => self.pending = error.message().to_string(),
+                    }
+                    None
+                }
+                (NewBranchStep::Revspec { name }, PromptKey::Action(PromptAction::Accept))
+                    if !prompt.input.is_empty() =>
+                {
+                    Some((name.clone(), prompt.input.trim().to_string()))
+                }
+                _ => None,
+            };
+
+            if let Some((name, start_point)) = create {
+                if let Err(error) = repo.create_branch(&name, &start_point) {
+                    let status = format!("couldn't create branch {name}: {error}");
+                    self.update_with_status_preserve_filter(terminal, status);
+                    return None;
+                }
+                if let Err(error) = self.reload_branches(repo, &name) {
+                    self.pending = format!("created branch but failed to refresh list: {error}");
+                }
+                prompt.step = NewBranchStep::Checkout { name };
+            }
+            self.new_branch = Some(prompt);
+            None
+        }
+
+        /// Handles a key press in whichever mode is active. Returns how to
+        /// leave the TUI if the key ended it.
+        fn handle_key(
+            &mut self,
+            key: KeyEvent,
+            config: &Config,
+            repo: &mut Repo,
+            terminal: &mut Tui,
+        ) -> Option<Exit> {
+            self.report = None;
+
+            // Help overlay
+            if let Some(help) = self.help.as_mut() {
+                match self.settings.keys.help.action_for(key) {
+                    Some(HelpAction::Close) => self.help = None,
+                    Some(HelpAction::Down) => help.scroll_down(1),
+                    Some(HelpAction::Up) => help.scroll_up(1),
+                    Some(HelpAction::PageDown) => help.scroll_down(help.height.max(1)),
+                    Some(HelpAction::PageUp) => help.scroll_up(help.height.max(1)),
+                    Some(HelpAction::First) => help.scroll = 0,
+                    Some(HelpAction::Last) => help.scroll_to_end(),
+                    None => {}
+                }
+                return None;
+            }
+
+            // Delete confirmation mode
+            if let Some(confirmation) = self.delete_confirmation.as_mut() {
+                let confirmed = if confirmation.needs_typing() {
+                    match prompt_key(&self.settings.keys.prompt, key) {
+                        PromptKey::Action(PromptAction::Cancel) => Some(false),
+                        PromptKey::Action(PromptAction::DeleteChar) => {
+                            confirmation.input.pop();
+                            None
+                        }
+                        PromptKey::Char(c) => {
+                            confirmation.input.push(c);
+                            None
+                        }
+                        PromptKey::Action(PromptAction::Accept)
+                            if confirmation.input == confirmation.branch_name =>
+                        {
+                            Some(true)
+                        }
+                        PromptKey::Action(PromptAction::Accept) => {
+                            confirmation.input.clear();
+                            None
+                        }
+                        PromptKey::Other => None,
+                    }
+                } else {
+                    match self.settings.keys.confirm.action_for(key) {
+                        Some(ConfirmAction::Yes) => Some(true),
+                        Some(ConfirmAction::No) => Some(false),
+                        _ => None,
+                    }
+                };
+                match confirmed {
+                    Some(true) => {
+                        let branch_name = confirmation.branch_name.clone();
+                        self.delete_confirmation = None;
+                        let status = match repo.delete_branch(&branch_name, UndoStep::new()) {
+                            // the deleted branch is gone, so the same row stays selected
+                            Ok(()) => match self.reload_branches(repo, &branch_name) {
+                                Ok(()) => {
+                                    format!("deleted branch: {branch_name}{}", self.undo_hint())
                                 }
-                                KeyCode::Char(c)
-                                    if key.modifiers.is_empty()
-                                        || key.modifiers == KeyModifiers::SHIFT =>
-                                {
-                                    self.filter.push(c);
-                                    self.update_filtered();
+                                Err(error) => {
+                                    format!("deleted branch but failed to refresh list: {error}")
                                 }
-                                _ => {}
-                            }
-                            continue;
+                            },
+                            Err(error) => format!("couldn't delete branch {branch_name}: {error}"),
+                        };
+                        self.update_with_status_preserve_filter(terminal, status);
+                    }
+                    Some(false) => {
+                        self.delete_confirmation = None;
+                        self.clear_pending_status(terminal);
+                    }
+                    None => {}
+                }
+                return None;
+            }
+
+            // New branch prompts
+            if self.new_branch.is_some() {
+                return self.handle_new_branch_key(key, config, repo, terminal);
+            }
+
+            // Clean up merged branches screen
+            if self.cleanup.is_some() {
+                self.handle_cleanup_key(key, repo, terminal);
+                return None;
+            }
+
+            // Inline rename editor
+            if self.rename.is_some() {
+                self.handle_rename_key(key, repo, terminal);
+                return None;
+            }
+
+            // New worktree path editor
+            if self.worktree_prompt.is_some() {
+                self.handle_worktree_key(key, repo, terminal);
+                return None;
+            }
+
+            // Branch checked out in another worktree
+            if let Some(info) = self.worktree_confirmation.clone() {
+                let path = info.worktree.unwrap_or_default();
+                match self.settings.keys.confirm.action_for(key) {
+                    Some(ConfirmAction::Yes) => return Some(Exit::Shell(path)),
+                    Some(ConfirmAction::Print) => return Some(Exit::PrintPath(path)),
+                    Some(ConfirmAction::No) => {
+                        self.worktree_confirmation = None;
+                        self.clear_pending_status(terminal);
+                    }
+                    _ => {}
+                }
+                return None;
+            }
+
+            // Dirty working tree confirmation mode
+            if let Some(info) = self.switch_confirmation.clone() {
+                let strategy = match self.settings.keys.confirm.action_for(key) {
+                    Some(ConfirmAction::Stash) => Some(SwitchStrategy::Stash),
+                    Some(ConfirmAction::Carry) => Some(SwitchStrategy::Carry),
+                    Some(ConfirmAction::Force) => Some(SwitchStrategy::Force),
+                    Some(ConfirmAction::No) => {
+                        self.switch_confirmation = None;
+                        self.clear_pending_status(terminal);
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(strategy) = strategy {
+                    self.switch_confirmation = None;
+                    if self.switch_branch(repo, terminal, &info, strategy) {
+                        return Some(Exit::Switched);
+                    }
+                }
+                return None;
+            }
+
+            // Filter mode: typing goes to the filter
+            if self.filter_mode {
+                match (self.settings.keys.filter.action_for(key), key.code) {
+                    (Some(FilterAction::Done), _) => {
+                        self.filter_mode = false;
+                    }
+                    (Some(FilterAction::DeleteChar), _) => {
+                        if self.filter.pop().is_none() {
+                            self.filter_mode = false;
                         }
+                        self.update_filtered();
+                    }
+                    (None, KeyCode::Char(c))
+                        if key.modifiers.is_empty()
+                            || key.modifiers == KeyModifiers::SHIFT =>
+                    {
+                        self.filter.push(c);
+                        self.update_filtered();
+                    }
+                    _ => {}
+                }
+                return None;
+            }
 
-                        // Normal mode
-                        match key.code {
-                            KeyCode::Enter => {
-                                match self.get_selected_branch_info() {
-                                    Ok(info) => {
-                                        if info.is_head {
-                                            let status = format!(
-                                                "already on branch '{}'",
-                                                info.branch_name
-                                            );
-                                            self.update_with_status_preserve_filter(
-                                                terminal, status,
-                                            );
-                                        } else {
-                                            let status = format!(
-                                                "switching to branch: {}",
-                                                info.branch_name
-                                            );
-                                            self.update_with_status(terminal, status);
-                                            match repo.change_branch(&info.branch_name) {
-                                                Ok(_) => return Ok(()),
-                                                Err(error) => {
-                                                    let status = format!(
-                                                        "couldn't change branch: {error}"
-                                                    );
-                                                    self.update_with_status_preserve_filter(
-                                                        terminal, status,
-                                                    );
-                                                }
-                                            }
-                                        }
-                                    }
-                                    Err(_) => {
-                                        let status = "no selection, nothing to do!".to_string();
-                                        self.update_with_status_preserve_filter(terminal, status);
-                                    }
-                                }
-                            }
-                            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
-                                return Ok(());
-                            }
-                            KeyCode::Char('D') if key.modifiers == KeyModifiers::SHIFT => {
-                                match self.get_selected_branch_info() {
-                                    Ok(info) => {
-                                        if info.is_head {
-                                            let status = format!(
-                                                "can't delete '{}': it is the current branch",
-                                                info.branch_name
-                                            );
-                                            self.update_with_status_preserve_filter(
-                                                terminal, status,
-                                            );
+            // Normal mode: keys are looked up in the key map, and
+            // unbound characters start filtering
+            let Some(action) = self.settings.keys.normal.action_for(key) else {
+                if let KeyCode::Char(c) = key.code {
+                    if key.modifiers.is_empty() || key.modifiers == KeyModifiers::SHIFT {
+                        self.filter_mode = true;
+                        self.filter.push(c);
+                        self.update_filtered();
+                    }
+                }
+                return None;
+            };
+            self.handle_action(action, config, repo, terminal)
+        }
+
+        /// Carries out an action of the branch list.
+        fn handle_action(
+            &mut self,
+            action: Action,
+            config: &Config,
+            repo: &mut Repo,
+            terminal: &mut Tui,
+        ) -> Option<Exit> {
+            match action {
+                Action::Help => self.help = Some(HelpScreen::default()),
+                Action::Switch => match self.get_selected_branch_info() {
+                    Ok(info) => return self.choose_branch(info, config, repo, terminal),
+                    Err(_) => {
+                        let status = "no selection, nothing to do!".to_string();
+                        self.update_with_status_preserve_filter(terminal, status);
+                    }
+                },
+                Action::Quit => {
+                    return Some(Exit::Quit);
+                }
+                Action::Delete if !self.items.marked.is_empty() => {
+                    self.start_batch(BatchOperation::Delete, repo, terminal);
+                }
+                Action::Delete => {
+                    match self.get_selected_branch_info() {
+                        Ok(info) => {
+                            if !info.is_branch() {
+                                let status = format!("can't delete {}", info.branch_name);
+                                self.update_with_status_preserve_filter(
+                                    terminal, status,
+                                );
+                            } else if info.is_head {
+                                let status = format!(
+                                    "can't delete '{}': it is the current branch",
+                                    info.branch_name
+                                );
+                                self.update_with_status_preserve_filter(
+                                    terminal, status,
+                                );
+                            } else if let Some(worktree) = info.worktree.as_deref() {
+                                let status = format!(
+                                    "can't delete '{}': it is checked out in {}",
+                                    info.branch_name,
+                                    worktree.display()
+                                );
+                                self.update_with_status_preserve_filter(
+                                    terminal, status,
+                                );
+                            } else if info.remote.is_some() {
+                                let status = format!(
+                                    "can't delete '{}': it is a remote-tracking branch",
+                                    info.branch_name
+                                );
+                                self.update_with_status_preserve_filter(
+                                    terminal, status,
+                                );
+                            } else {
+                                match self.unmerged_commits(repo, &info.branch_name) {
+                                    Ok(unmerged_commits) => {
+                                        let status = if unmerged_commits == 0 {
+                                            format!("delete branch {}?", info.branch_name)
                                         } else {
-                                            self.delete_confirmation =
-                                                Some(info.branch_name.clone());
-                                            let status = format!(
-                                                "confirm deleting branch {}? press Y to delete or N to cancel",
-                                                info.branch_name
-                                            );
-                                            self.update_with_status_preserve_filter(
-                                                terminal, status,
-                                            );
-                                        }
+                                            String::new()
+                                        };
+                                        self.delete_confirmation = Some(DeleteConfirmation {
+                                            branch_name: info.branch_name,
+                                            unmerged_commits,
+                                            input: String::new(),
+                                        });
+                                        self.update_with_status_preserve_filter(
+                                            terminal, status,
+                                        );
                                     }
-                                    Err(_) => {
-                                        let status = "no selection, nothing to delete!".to_string();
-                                        self.update_with_status_preserve_filter(terminal, status);
+                                    Err(error) => {
+                                        let status = format!(
+                                            "can't count unmerged commits of {}: {}",
+                                            info.branch_name,
+                                            error.message()
+                                        );
+                                        self.update_with_status_preserve_filter(
+                                            terminal, status,
+                                        );
                                     }
                                 }
                             }
-                            KeyCode::Char('/') => {
-                                self.filter_mode = true;
-                            }
-                            KeyCode::Down | KeyCode::Char('j') => self.items.next(),
-                            KeyCode::Up | KeyCode::Char('k') => self.items.previous(),
-                            KeyCode::PageDown => self.items.page_down(PAGE_SIZE),
-                            KeyCode::PageUp => self.items.page_up(PAGE_SIZE),
-                            KeyCode::Home | KeyCode::Char('g') => self.items.go_to_first(),
-                            KeyCode::End | KeyCode::Char('G') => self.items.go_to_last(),
-                            KeyCode::Left => self.items.unselect(),
-                            KeyCode::Backspace => {
-                                self.filter.pop();
-                                self.update_filtered();
-                            }
-                            _ => {}
                         }
+                        Err(_) => {
+                            let status = "no selection, nothing to delete!".to_string();
+                            self.update_with_status_preserve_filter(terminal, status);
+                        }
+                    }
+                }
+                Action::Undo => self.undo_deletion(repo, terminal),
+                Action::Filter => {
+                    self.filter_mode = true;
+                }
+                Action::Rename => match self.get_selected_branch_info() {
+                    Ok(info) if !info.is_branch() => {
+                        let status = format!("can't rename {}", info.branch_name);
+                        self.update_with_status_preserve_filter(terminal, status);
+                    }
+                    Ok(info) if info.remote.is_some() => {
+                        let status = format!(
+                            "can't rename '{}': it is a remote-tracking branch",
+                            info.branch_name
+                        );
+                        self.update_with_status_preserve_filter(terminal, status);
+                    }
+                    Ok(info) => {
+                        self.pending.clear();
+                        self.rename = Some(RenamePrompt {
+                            input: info.branch_name.clone(),
+                            branch_name: info.branch_name,
+                            confirm_remote: false,
+                        });
+                    }
+                    Err(_) => {
+                        let status = "no selection, nothing to rename!".to_string();
+                        self.update_with_status_preserve_filter(terminal, status);
+                    }
+                },
+                Action::AddWorktree => match self.get_selected_branch_info() {
+                    Ok(info) if info.remote.is_some() || info.is_head => {
+                        let status = format!(
+                            "can't add a worktree for '{}': it is {}",
+                            info.branch_name,
+                            if info.is_head {
+                                "the current branch"
+                            } else {
+                                "a remote-tracking branch"
+                            }
+                        );
+                        self.update_with_status_preserve_filter(terminal, status);
+                    }
+                    Ok(info) if info.worktree.is_some() => {
+                        let worktree = info.worktree.clone().unwrap_or_default();
+                        let status = format!(
+                            "{} is checked out in {} already.",
+                            info.branch_name,
+                            worktree.display()
+                        );
+                        self.worktree_confirmation = Some(info);
+                        self.update_with_status_preserve_filter(terminal, status);
+                    }
+                    Ok(info) => {
+                        self.pending.clear();
+                        let path = repo.default_worktree_path(&info.branch_name);
+                        self.worktree_prompt = Some(WorktreePrompt {
+                            input: path.display().to_string(),
+                            branch_name: info.branch_name,
+                        });
+                    }
+                    Err(_) => {
+                        let status = "no selection, nothing to do!".to_string();
+                        self.update_with_status_preserve_filter(terminal, status);
+                    }
+                },
+                Action::CleanupMerged if self.details.is_some() => {
+                    let status = format!(
+                        "still checking which branches are merged into {}, try again in a moment",
+                        repo.base_branch()
+                    );
+                    self.update_with_status_preserve_filter(terminal, status);
+                }
+                Action::CleanupMerged => {
+                    let merged: Vec<String> = self
+                        .items
+                        .items
+                        .iter()
+                        .filter(|b| b.merged && !b.is_head && b.worktree.is_none())
+                        .map(|b| b.branch_name.clone())
+                        .collect();
+                    if merged.is_empty() {
+                        let status = format!(
+                            "no branches merged into {}",
+                            repo.base_branch()
+                        );
+                        self.update_with_status_preserve_filter(terminal, status);
+                    } else {
+                        let title = format!(
+                            "clean up branches merged into {}",
+                            repo.base_branch()
+                        );
+                        let cleanup = CleanupScreen::new(title, merged, BatchOperation::Delete);
+                        self.open_checklist(cleanup, false, repo, terminal);
+                    }
+                }
+                Action::CleanupGone => {
+                    let gone: Vec<String> = self
+                        .items
+                        .items
+                        .iter()
+                        .filter(|b| b.upstream_gone && !b.is_head && b.worktree.is_none())
+                        .map(|b| b.branch_name.clone())
+                        .collect();
+                    if gone.is_empty() {
+                        let status = "no branches with a gone upstream".to_string();
+                        self.update_with_status_preserve_filter(terminal, status);
+                    } else {
+                        let title =
+                            "clean up branches whose upstream is gone".to_string();
+                        let cleanup = CleanupScreen::new(title, gone, BatchOperation::Delete);
+                        self.open_checklist(cleanup, false, repo, terminal);
+                    }
+                }
+                Action::GoneOnly => self.toggle_gone_only(),
+                Action::Mark => self.items.toggle_mark(),
+                Action::MarkRange => self.items.mark_range(),
+                Action::MarkAll => self.items.toggle_mark_all(),
+                Action::Push => self.start_batch(BatchOperation::Push, repo, terminal),
+                Action::Fetch => self.start_batch(BatchOperation::Fetch, repo, terminal),
+                Action::Rebase => self.start_batch(BatchOperation::Rebase, repo, terminal),
+                Action::NewBranch => {
+                    self.pending.clear();
+                    self.new_branch = Some(NewBranchPrompt {
+                        step: NewBranchStep::Name,
+                        input: String::new(),
+                        highlighted: self.get_selected_branch_info().ok(),
+                    });
+                }
+                Action::Down => self.items.next(),
+                Action::Up => self.items.previous(),
+                Action::PageDown => self.items.page_down(self.settings.page_size),
+                Action::PageUp => self.items.page_up(self.settings.page_size),
+                Action::First => self.items.go_to_first(),
+                Action::Last => self.items.go_to_last(),
+                Action::Unselect => self.items.unselect(),
+                Action::TogglePreview => self.show_preview = !self.show_preview,
+                Action::PreviewMode => self.toggle_preview_mode(),
+                Action::PreviewDown => self.scroll_preview_down(),
+                Action::PreviewUp => self.scroll_preview_up(),
+                Action::ShrinkFilter => {
+                    self.filter.pop();
+                    self.update_filtered();
+                }
+            }
+            None
+        }
+
+        /// Handles a mouse event: clicks select rows and answer confirmations,
+        /// double-clicks switch and the wheel scrolls whatever is under it.
+        fn handle_mouse(
+            &mut self,
+            mouse: MouseEvent,
+            config: &Config,
+            repo: &mut Repo,
+            terminal: &mut Tui,
+        ) -> Option<Exit> {
+            let (column, row) = (mouse.column, mouse.row);
+            let over = |area: Rect| {
+                column >= area.x && column < area.right() && row >= area.y && row < area.bottom()
+            };
+            let down = match mouse.kind {
+                MouseEventKind::ScrollDown => true,
+                MouseEventKind::ScrollUp => false,
+                MouseEventKind::Down(MouseButton::Left) => {
+                    return self.handle_click(column, row, config, repo, terminal);
+                }
+                _ => return None,
+            };
+
+            if let Some(help) = self.help.as_mut() {
+                if down {
+                    help.scroll_down(1);
+                } else {
+                    help.scroll_up(1);
+                }
+            } else if let Some(cleanup) = self.cleanup.as_mut() {
+                if down {
+                    cleanup.next();
+                } else {
+                    cleanup.previous();
+                }
+            } else if over(self.areas.preview) {
+                if down {
+                    self.scroll_preview_down();
+                } else {
+                    self.scroll_preview_up();
+                }
+            } else if over(self.areas.list) {
+                if down {
+                    self.items.next();
+                } else {
+                    self.items.previous();
+                }
+            }
+            None
+        }
+
+        fn handle_click(
+            &mut self,
+            column: u16,
+            row: u16,
+            config: &Config,
+            repo: &mut Repo,
+            terminal: &mut Tui,
+        ) -> Option<Exit> {
+            if self.help.is_some() {
+                return None;
+            }
+
+            // a click on a button in the status bar presses its key
+            if row == self.areas.status.y {
+                let action = self
+                    .areas
+                    .buttons
+                    .iter()
+                    .find(|(columns, _)| columns.contains(&column))
+                    .map(|(_, action)| *action)?;
+                let key = self.settings.keys.confirm.keys_for(action).next()?;
+                let key = KeyEvent::new(key.code, key.modifiers);
+                return self.handle_key(key, config, repo, terminal);
+            }
+
+            if let Some(cleanup) = self.cleanup.as_mut() {
+                let index = list_row_at(self.areas.list, cleanup.state.offset(), column, row)?;
+                if index < cleanup.branches.len() {
+                    cleanup.state.select(Some(index));
+                }
+                return None;
+            }
+
+            // rows can't be picked while a prompt or confirmation is open
+            if self.delete_confirmation.is_some()
+                || self.switch_confirmation.is_some()
+                || self.worktree_confirmation.is_some()
+                || self.worktree_prompt.is_some()
+                || self.new_branch.is_some()
+                || self.rename.is_some()
+            {
+                return None;
+            }
+            let index = list_row_at(self.areas.list, self.items.state.offset(), column, row)?;
+            if index >= self.filtered_len() {
+                return None;
+            }
+            self.items.state.select(Some(index));
+            let double_click = self
+                .last_click
+                .is_some_and(|(at, clicked)| clicked == index && at.elapsed() < DOUBLE_CLICK);
+            if double_click {
+                self.last_click = None;
+                return self.handle_action(Action::Switch, config, repo, terminal);
+            }
+            self.last_click = Some((Instant::now(), index));
+            None
+        }
+
+        /// Runs the event loop until the user switches branches or quits. In
+        /// `--print` mode choosing a branch returns it instead of checking it out.
+        ///
+        /// # Errors
+        ///
+        /// Will return `Err` if `self.ui()` failed.
+        ///
+        pub fn run_app(
+            &mut self,
+            config: &Config,
+            repo: &mut Repo,
+            terminal: &mut Tui,
+        ) -> io::Result<Exit> {
+            let mut last_tick = Instant::now();
+            let mut redraw = true;
+            loop {
+                // polling for details mustn't redraw the list when none arrived
+                if self.receive_details() || redraw {
+                    self.refresh_preview(repo);
+                    terminal.draw(|f| self.ui(f))?;
+                }
+                redraw = false;
+
+                let mut timeout = config
+                    .tick_rate()
+                    .checked_sub(last_tick.elapsed())
+                    .unwrap_or_else(|| Duration::from_secs(0));
+                // pick up branch details as they arrive rather than once a tick
+                if self.details.is_some() {
+                    timeout = timeout.min(DETAILS_POLL);
+                }
+                if event::poll(timeout)? {
+                    redraw = true;
+                    let exit = match event::read()? {
+                        Event::Key(key) => self.handle_key(key, config, repo, terminal),
+                        Event::Mouse(mouse) => self.handle_mouse(mouse, config, repo, terminal),
+                        _ => None,
+                    };
+                    if let Some(exit) = exit {
+                        return Ok(exit);
                     }
                 }
                 if last_tick.elapsed() >= config.tick_rate() {
                     last_tick = Instant::now();
+                    redraw = true;
                 }
             }
         }